@@ -0,0 +1,89 @@
+use std::{borrow::Cow, collections::HashMap, io, path::Path};
+
+use process_mining::event_log::{AttributeValue, Trace, XESEditableAttribute};
+use regex::Regex;
+
+/// Prefix marking a regex pattern in the mapping file (e.g., `re:^TX(\d+)$`)
+const REGEX_PREFIX: &str = "re:";
+
+/// Mapping of activity labels (i.e., event `concept:name` values) to new labels
+///
+/// Exact matches take precedence over regex patterns, which are tried in the order of the mapping file.
+/// Activities not matched by any entry stay unchanged.
+pub struct ActivityMapping {
+    exact: HashMap<String, String>,
+    /// Regex patterns (matching the complete label) and their replacement (which can reference capture groups, e.g., `$1`)
+    patterns: Vec<(Regex, String)>,
+    /// Remove events with an activity mapped to the empty label
+    drop_empty: bool,
+}
+
+impl ActivityMapping {
+    /// Load the mapping from a CSV file without header, with rows `<activity or re:pattern>,<new label>`
+    ///
+    /// Lines starting with `#` are ignored
+    pub fn load(path: &Path, drop_empty: bool) -> io::Result<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .comment(Some(b'#'))
+            .from_path(path)?;
+        let mut exact = HashMap::new();
+        let mut patterns = Vec::new();
+        for row in reader.records() {
+            let row = row?;
+            let (Some(from), Some(to)) = (row.get(0), row.get(1)) else {
+                let line = row.position().map(|p| p.line()).unwrap_or_default();
+                return Err(io::Error::other(format!(
+                    "Expected two columns in line {line} of activity mapping"
+                )));
+            };
+            match from.strip_prefix(REGEX_PREFIX) {
+                Some(pattern) => {
+                    let regex = Regex::new(&format!("^(?:{pattern})$")).map_err(|e| {
+                        io::Error::other(format!("Invalid pattern {pattern:?}: {e}"))
+                    })?;
+                    patterns.push((regex, to.to_string()));
+                }
+                None => {
+                    exact.insert(from.to_string(), to.to_string());
+                }
+            }
+        }
+        Ok(Self {
+            exact,
+            patterns,
+            drop_empty,
+        })
+    }
+
+    /// New label of the activity (or `None` if it is not mapped)
+    pub fn label<'a>(&'a self, activity: &'a str) -> Option<Cow<'a, str>> {
+        if let Some(label) = self.exact.get(activity) {
+            return Some(Cow::Borrowed(label));
+        }
+        self.patterns
+            .iter()
+            .find(|(regex, _)| regex.is_match(activity))
+            .map(|(regex, replacement)| regex.replace(activity, replacement.as_str()))
+    }
+
+    /// Rename the activities of all events of the trace (and drop events mapped to the empty label, if enabled)
+    pub fn apply(&self, trace: &mut Trace) {
+        trace.events.retain_mut(|e| {
+            let Some(attr) = e.attributes.get_by_key_mut("concept:name") else {
+                return true;
+            };
+            let AttributeValue::String(activity) = &attr.value else {
+                return true;
+            };
+            match self.label(activity) {
+                Some(label) if label.is_empty() && self.drop_empty => false,
+                Some(label) => {
+                    attr.value = AttributeValue::String(label.into_owned());
+                    true
+                }
+                None => true,
+            }
+        });
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use process_mining::event_log::Trace;
+use serde::{Deserialize, Serialize};
+
+use crate::variants::{Variant, VariantInterner};
+
+/// How traces of variants (activity sequences) occurring fewer than k times are handled
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KAnonymityMode {
+    /// Remove the traces
+    #[default]
+    Suppress,
+    /// Truncate the traces to the longest prefix which is the (generalized) activity sequence of at least k traces,
+    /// removing them only if there is no such prefix
+    GeneralizePrefix,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct VariantInfo {
+    /// Number of traces with this variant (in the counting pass)
+    count: u64,
+    /// Number of events kept of traces with this variant (None: traces are removed)
+    kept_events: Option<usize>,
+}
+
+/// Ensures that each activity sequence in the output occurs at least `k` times (or not at all)
+///
+/// This needs two passes over the traces: In the first (counting) pass, the variants are counted using [`KAnonymizer::apply`]
+/// (which then drops all traces). After [`KAnonymizer::finish`], the second pass applies the decision for the variant of each trace.
+/// The second pass has to see the same traces as the first one (i.e., the previous steps have to be deterministic).
+///
+/// Only the distinct variants (not the traces) are held in memory.
+#[derive(Debug, Clone)]
+pub struct KAnonymizer {
+    k: u64,
+    mode: KAnonymityMode,
+    counting: bool,
+    interner: VariantInterner,
+    variants: HashMap<Variant, VariantInfo>,
+    /// Number of variants occurring fewer than k times
+    pub rare_variants: u64,
+    /// Number of removed traces (in the second pass)
+    pub suppressed_traces: u64,
+    /// Number of truncated traces (in the second pass)
+    pub generalized_traces: u64,
+}
+
+impl KAnonymizer {
+    pub fn new(k: u64, mode: KAnonymityMode) -> Self {
+        Self {
+            k,
+            mode,
+            counting: true,
+            interner: VariantInterner::default(),
+            variants: HashMap::new(),
+            rare_variants: 0,
+            suppressed_traces: 0,
+            generalized_traces: 0,
+        }
+    }
+
+    pub fn k(&self) -> u64 {
+        self.k
+    }
+
+    /// Count the variant of the trace (first pass) or remove/truncate it if its variant is rare (second pass)
+    ///
+    /// Returns `false` if the trace should be dropped (i.e., always in the first pass)
+    pub fn apply(&mut self, trace: &mut Trace) -> bool {
+        if self.counting {
+            let variant = self.interner.intern(trace);
+            self.variants.entry(variant).or_default().count += 1;
+            return false;
+        }
+        // Variants not seen while counting can not be checked, so they are removed
+        let kept_events = self
+            .interner
+            .lookup(trace)
+            .and_then(|variant| self.variants.get(&variant))
+            .and_then(|info| info.kept_events);
+        let Some(kept_events) = kept_events else {
+            self.suppressed_traces += 1;
+            return false;
+        };
+        if kept_events < trace.events.len() {
+            trace.events.truncate(kept_events);
+            self.generalized_traces += 1;
+        }
+        true
+    }
+
+    /// Finish the counting pass, deciding how traces of each variant are handled in the second pass
+    ///
+    /// If the variants were already counted (i.e., the pass counted the variants for a later k-anonymity step),
+    /// only the counts of removed and truncated traces are reset for the next pass
+    pub fn finish(&mut self) {
+        self.suppressed_traces = 0;
+        self.generalized_traces = 0;
+        if !self.counting {
+            return;
+        }
+        self.counting = false;
+        for (variant, info) in self.variants.iter_mut() {
+            if info.count < self.k {
+                self.rare_variants += 1;
+            } else {
+                info.kept_events = Some(variant.len());
+            }
+        }
+        if self.mode == KAnonymityMode::GeneralizePrefix {
+            self.generalize_prefixes();
+        }
+    }
+
+    /// Decide the kept prefix of each variant using a prefix tree of all variants
+    ///
+    /// Starting from the leaves, the traces ending at a node (i.e., of the variant of the node or moved up from its children)
+    /// are moved up to the parent node as long as there are fewer than k of them.
+    /// Traces moved up to the root (i.e., the empty prefix) are removed (except for a frequent variant of empty traces).
+    fn generalize_prefixes(&mut self) {
+        struct Node {
+            parent: usize,
+            depth: usize,
+            children: HashMap<u32, usize>,
+            /// Variants whose traces currently end at this node
+            variants: Vec<usize>,
+        }
+        let mut variants: Vec<(&Variant, &mut VariantInfo)> = self.variants.iter_mut().collect();
+        let mut nodes = vec![Node {
+            parent: 0,
+            depth: 0,
+            children: HashMap::new(),
+            variants: Vec::new(),
+        }];
+        for (i, (variant, _)) in variants.iter().enumerate() {
+            let mut node = 0;
+            for activity in variant.iter() {
+                node = match nodes[node].children.get(activity) {
+                    Some(&child) => child,
+                    None => {
+                        let child = nodes.len();
+                        let depth = nodes[node].depth + 1;
+                        nodes[node].children.insert(*activity, child);
+                        nodes.push(Node {
+                            parent: node,
+                            depth,
+                            children: HashMap::new(),
+                            variants: Vec::new(),
+                        });
+                        child
+                    }
+                };
+            }
+            nodes[node].variants.push(i);
+        }
+        // Children are always created after their parent, so iterating in reverse visits all children before their parent
+        for node in (1..nodes.len()).rev() {
+            let count: u64 = nodes[node]
+                .variants
+                .iter()
+                .map(|&i| variants[i].1.count)
+                .sum();
+            if count < self.k {
+                let moved = std::mem::take(&mut nodes[node].variants);
+                let parent = nodes[node].parent;
+                nodes[parent].variants.extend(moved);
+            }
+        }
+        for node in &nodes[1..] {
+            for &i in &node.variants {
+                variants[i].1.kept_events = Some(node.depth);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use process_mining::event_log::{Attributes, Event};
+
+    use super::*;
+    use crate::filter::activity;
+
+    fn trace(activities: &str) -> Trace {
+        Trace {
+            attributes: Attributes::new(),
+            events: activities
+                .chars()
+                .map(|a| Event::new(a.to_string()))
+                .collect(),
+        }
+    }
+
+    fn activities(trace: &Trace) -> String {
+        trace
+            .events
+            .iter()
+            .map(|e| activity(e).unwrap().as_str())
+            .collect()
+    }
+
+    /// Run both passes over traces with the given activity sequences, returning the output traces
+    fn anonymize(k: u64, mode: KAnonymityMode, input: &[&str]) -> Vec<String> {
+        let mut anonymizer = KAnonymizer::new(k, mode);
+        for t in input {
+            assert!(!anonymizer.apply(&mut trace(t)));
+        }
+        anonymizer.finish();
+        input
+            .iter()
+            .filter_map(|t| {
+                let mut trace = trace(t);
+                anonymizer.apply(&mut trace).then(|| activities(&trace))
+            })
+            .collect()
+    }
+
+    const INPUT: [&str; 6] = ["ABC", "ABD", "ABC", "AE", "ABD", "ABC"];
+
+    #[test]
+    fn suppress_rare_variants() {
+        assert_eq!(
+            anonymize(3, KAnonymityMode::Suppress, &INPUT),
+            vec!["ABC", "ABC", "ABC"]
+        );
+        assert_eq!(anonymize(1, KAnonymityMode::Suppress, &INPUT), INPUT);
+    }
+
+    #[test]
+    fn generalize_rare_variants_to_prefixes() {
+        // ABD (2 traces) and AE (1 trace) are rare, but together with the prefix A there are 3 traces
+        assert_eq!(
+            anonymize(3, KAnonymityMode::GeneralizePrefix, &INPUT),
+            vec!["ABC", "A", "ABC", "A", "A", "ABC"]
+        );
+        // AE is the only trace with the prefix A (besides the frequent variants), so it is removed
+        assert_eq!(
+            anonymize(2, KAnonymityMode::GeneralizePrefix, &INPUT),
+            vec!["ABC", "ABD", "ABC", "ABD", "ABC"]
+        );
+    }
+
+    #[test]
+    fn generalize_without_frequent_prefix() {
+        // No prefix is shared by k traces, so all traces are removed
+        assert!(anonymize(3, KAnonymityMode::GeneralizePrefix, &["AB", "AC", "B"]).is_empty());
+        assert_eq!(
+            anonymize(2, KAnonymityMode::GeneralizePrefix, &["AB", "AC", "B"]),
+            vec!["A", "A"]
+        );
+    }
+}
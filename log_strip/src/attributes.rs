@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+
+use process_mining::event_log::Attributes;
+
+/// List of attribute keys (or glob patterns like `org:*`) to keep
+#[derive(Debug, Clone, Default)]
+pub struct KeepList {
+    patterns: Vec<String>,
+}
+
+impl KeepList {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self { patterns }
+    }
+
+    /// Additionally keep the given key (e.g., for attributes created by transforms)
+    pub fn add(&mut self, key: &str) {
+        self.patterns.push(key.to_string());
+    }
+
+    /// Check if the attribute key matches any of the patterns
+    pub fn matches(&self, key: &str) -> bool {
+        self.patterns.iter().any(|p| glob_match(p, key))
+    }
+
+    /// Check if an attribute key with the given prefix (e.g., `org` for `org:resource`) may match any of the patterns
+    pub fn may_match_prefix(&self, prefix: &str) -> bool {
+        let prefix = format!("{prefix}:");
+        self.patterns
+            .iter()
+            .any(|p| p.starts_with(&prefix) || glob_match(p, &prefix))
+    }
+
+    /// Patterns without wildcards (i.e., exact attribute keys)
+    pub fn exact_keys(&self) -> impl Iterator<Item = &str> {
+        self.patterns
+            .iter()
+            .filter(|p| !is_glob(p))
+            .map(|p| p.as_str())
+    }
+
+    /// Check if no pattern contains wildcards (i.e., all kept keys are known)
+    pub fn is_exact(&self) -> bool {
+        !self.patterns.iter().any(|p| is_glob(p))
+    }
+
+    /// Allowlist to pass directly to the XES parser, additionally including the `required` keys (e.g., needed for filtering)
+    ///
+    /// Only possible if no pattern contains wildcards, as the parser only supports exact keys.
+    /// Otherwise, `None` is returned.
+    /// In both cases, the attributes still have to be filtered after parsing (see [`KeepList::retain`]).
+    pub fn as_import_allowlist(&self, required: &[&str]) -> Option<HashSet<String>> {
+        if !self.is_exact() {
+            None
+        } else {
+            Some(
+                self.patterns
+                    .iter()
+                    .cloned()
+                    .chain(required.iter().map(|k| k.to_string()))
+                    .collect(),
+            )
+        }
+    }
+
+    /// Remove all attributes whose key does not match any pattern
+    pub fn retain(&self, attributes: &mut Attributes) {
+        attributes.retain(|a| self.matches(&a.key));
+    }
+}
+
+fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Match `text` against a glob `pattern`, where `*` matches any sequence of characters and `?` matches exactly one character
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last seen `*` in the pattern and of the text when it was seen (for backtracking)
+    let mut last_star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            last_star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = last_star {
+            // Let the last `*` consume one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            last_star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_literal() {
+        assert!(glob_match("concept:name", "concept:name"));
+        assert!(!glob_match("concept:name", "concept:names"));
+        assert!(!glob_match("concept:names", "concept:name"));
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("org:*", "org:resource"));
+        assert!(glob_match("org:*", "org:"));
+        assert!(!glob_match("org:*", "concept:name"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("*:name", "concept:name"));
+    }
+
+    #[test]
+    fn glob_match_backtracking() {
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(glob_match("*ab", "aab"));
+        assert!(glob_match("**?", "x"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use indicatif::{MultiProgress, ProgressBar};
+
+use crate::{
+    attributes::glob_match,
+    error::StripError,
+    pipeline::{Pipeline, RunSummary},
+};
+
+/// Options for processing many inputs independently
+#[derive(clap::Args, Debug, Clone)]
+#[command(next_help_heading = "Batch Processing")]
+pub struct BatchArgs {
+    /// Process each input separately, writing the outputs (with the same file names) to this directory
+    ///
+    /// Inputs can be files, directories (processing all XES and CSV files inside) or glob patterns (e.g., `'logs/*.xes.gz'`)
+    #[clap(long, value_name = "DIR", conflicts_with = "output")]
+    pub output_dir: Option<PathBuf>,
+    /// Maximum number of inputs processed in parallel (default: number of CPUs)
+    #[clap(long, value_name = "N", requires = "output_dir")]
+    pub jobs: Option<usize>,
+}
+
+fn is_supported_input(file_name: &str) -> bool {
+    [".xes", ".xes.gz", ".csv"]
+        .iter()
+        .any(|ext| file_name.ends_with(ext))
+}
+
+/// Files in the directory matching the predicate (sorted by path)
+fn list_files<F: Fn(&str) -> bool>(dir: &Path, predicate: F) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() && predicate(&entry.file_name().to_string_lossy()) {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn input_missing(path: &Path) -> impl FnOnce(io::Error) -> StripError {
+    let path = path.to_path_buf();
+    move |error| StripError::InputMissing { path, error }
+}
+
+/// Expand directories (to all XES and CSV files inside) and glob patterns in the file name (e.g., `logs/*.xes`)
+pub fn expand_inputs(paths: &[PathBuf]) -> Result<Vec<PathBuf>, StripError> {
+    let mut inputs = Vec::new();
+    for path in paths {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            inputs.extend(list_files(path, is_supported_input).map_err(input_missing(path))?);
+        } else if file_name.contains(['*', '?']) {
+            let dir = match path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            let matches =
+                list_files(dir, |name| glob_match(&file_name, name)).map_err(input_missing(dir))?;
+            if matches.is_empty() {
+                return Err(input_missing(path)(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No matching files",
+                )));
+            }
+            inputs.extend(matches);
+        } else {
+            inputs.push(path.clone());
+        }
+    }
+    Ok(inputs)
+}
+
+/// Output path of an input in the output directory (with the same file name)
+///
+/// Returns an error if multiple inputs have the same file name (as they would overwrite each other)
+pub fn output_paths(inputs: &[PathBuf], output_dir: &Path) -> Result<Vec<PathBuf>, StripError> {
+    let mut seen = HashSet::new();
+    inputs
+        .iter()
+        .map(|input| {
+            let file_name = input.file_name().unwrap_or_default();
+            if !seen.insert(file_name) {
+                return Err(StripError::Config(format!(
+                    "Multiple inputs are named {file_name:?}"
+                )));
+            }
+            Ok(output_dir.join(file_name))
+        })
+        .collect()
+}
+
+/// Run the pipeline for each input separately, with at most `jobs` inputs processed in parallel
+///
+/// Returns the result of each input (in the order of the inputs)
+pub fn run_batch(
+    pipeline: &Pipeline,
+    inputs: &[PathBuf],
+    outputs: &[PathBuf],
+    jobs: usize,
+) -> Vec<Result<RunSummary, StripError>> {
+    let multi_progress = MultiProgress::new();
+    let next_input = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<RunSummary, StripError>>>> =
+        Mutex::new(inputs.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, inputs.len().max(1)) {
+            scope.spawn(|| loop {
+                // Each worker takes the next unprocessed input until all are done
+                let i = next_input.fetch_add(1, Ordering::Relaxed);
+                let (Some(input), Some(output)) = (inputs.get(i), outputs.get(i)) else {
+                    break;
+                };
+                let name = input.file_name().unwrap_or_default().to_string_lossy();
+                let progress = multi_progress.add(ProgressBar::new_spinner());
+                progress.set_message(format!("Progressing {name}..."));
+                let result = pipeline.run(std::slice::from_ref(input), output, &progress);
+                progress.finish_with_message(match &result {
+                    Ok(_) => format!("Finished {name}"),
+                    Err(_) => format!("Failed {name}"),
+                });
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("All inputs are processed"))
+        .collect()
+}
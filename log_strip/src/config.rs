@@ -0,0 +1,300 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    activities::ActivityMapping,
+    anonymity::KAnonymityMode,
+    attributes::KeepList,
+    enrich::Enrichment,
+    filter::{EventFilter, TraceFilter},
+    order::OrderArgs,
+    pipeline::Step,
+    pseudonymize::{self, Pseudonymizer},
+    sample::Sampling,
+    timestamps::{self, TimeGranularity},
+};
+
+/// Environment variable from which the seed is read if neither `seed` nor `seed_file` is set
+pub const SEED_ENV_VAR: &str = "LOG_STRIP_SEED";
+
+/// Declarative description of the transformation steps, e.g., read from a TOML or JSON pipeline file
+///
+/// ```toml
+/// [[steps]]
+/// type = "filter_traces"
+/// min_events = 2
+///
+/// [[steps]]
+/// type = "keep_attributes"
+/// trace = ["concept:name"]
+/// event = ["concept:name", "time:timestamp", "org:*"]
+///
+/// [[steps]]
+/// type = "coarsen_time"
+/// granularity = "hour"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// Seed for random operations (if not set, it is read from `seed_file` or [`SEED_ENV_VAR`], or a random seed is used)
+    ///
+    /// The offsets of `shift_time` can be recomputed from the seed, so pipeline files under version control
+    /// should use `seed_file` or [`SEED_ENV_VAR`] instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// File containing the seed (relative paths are resolved against the directory of the pipeline file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_file: Option<PathBuf>,
+    /// Timezone of dates without UTC offset in the inputs (IANA name; UTC if not set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assume_timezone: Option<String>,
+    /// Timezone in which dates are coarsened and written (IANA name; UTC if not set)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_timezone: Option<String>,
+    /// Steps applied to each trace, in this order
+    #[serde(default)]
+    pub steps: Vec<StepConfig>,
+}
+
+/// Configuration of a single step (see [`Step`] for the loaded version)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StepConfig {
+    OrderEvents(OrderArgs),
+    RenameActivities {
+        /// CSV mapping file (relative paths are resolved against the directory of the pipeline file)
+        mapping: PathBuf,
+        #[serde(default)]
+        drop_empty: bool,
+    },
+    CollapseLifecycle,
+    FilterEvents(EventFilter),
+    FilterTraces(TraceFilter),
+    Sample {
+        fraction: Option<f64>,
+        count: Option<usize>,
+    },
+    /// Attribute keys (or glob patterns) to keep per level; levels without a list keep all attributes
+    KeepAttributes {
+        log: Option<Vec<String>>,
+        trace: Option<Vec<String>>,
+        event: Option<Vec<String>>,
+    },
+    ShiftTime {
+        /// Maximum offset (e.g., `30d`)
+        max_shift: String,
+    },
+    CoarsenTime {
+        granularity: TimeGranularity,
+        #[serde(default)]
+        round: bool,
+    },
+    Enrich {
+        attributes: Vec<Enrichment>,
+    },
+    /// Attribute lists default to those of the command line (`concept:name` of traces, `org:resource` of events)
+    Pseudonymize {
+        #[serde(default = "default_pseudonymized_trace_attrs")]
+        trace_attrs: Vec<String>,
+        #[serde(default = "default_pseudonymized_event_attrs")]
+        event_attrs: Vec<String>,
+        /// File containing the secret key (if not set, the key is read from the environment)
+        key_file: Option<PathBuf>,
+    },
+    /// Requires an additional pass over the input(s) (see [`Step::KAnonymity`])
+    KAnonymity {
+        k: u64,
+        #[serde(default)]
+        mode: KAnonymityMode,
+    },
+}
+
+fn parse_optional_timezone(tz: &Option<String>) -> io::Result<Tz> {
+    match tz {
+        Some(tz) => timestamps::parse_timezone(tz)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        None => Ok(Tz::UTC),
+    }
+}
+
+fn default_pseudonymized_trace_attrs() -> Vec<String> {
+    vec!["concept:name".to_string()]
+}
+
+fn default_pseudonymized_event_attrs() -> Vec<String> {
+    vec!["org:resource".to_string()]
+}
+
+impl PipelineConfig {
+    /// Read a pipeline file: JSON if the file ends with `.json`, otherwise TOML
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let mut config: PipelineConfig = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(io::Error::other)?
+        } else {
+            toml::from_str(&content).map_err(io::Error::other)?
+        };
+        // Referenced files are relative to the pipeline file
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        if let Some(seed_file) = &mut config.seed_file {
+            *seed_file = base_dir.join(&seed_file);
+        }
+        for step in &mut config.steps {
+            match step {
+                StepConfig::RenameActivities { mapping, .. } => *mapping = base_dir.join(&mapping),
+                StepConfig::Pseudonymize {
+                    key_file: Some(key_file),
+                    ..
+                } => *key_file = base_dir.join(&key_file),
+                _ => {}
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("Pipeline configs can be serialized as TOML")
+    }
+
+    /// Seed from `seed`, `seed_file` or [`SEED_ENV_VAR`] (in this order), if any of them is set
+    pub fn load_seed(&self) -> io::Result<Option<u64>> {
+        let invalid = |source: String, e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid seed in {source}: {e}"),
+            )
+        };
+        match (self.seed, &self.seed_file) {
+            (Some(_), Some(_)) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Only one of seed and seed_file can be set",
+            )),
+            (Some(seed), None) => Ok(Some(seed)),
+            (None, Some(path)) => {
+                let seed = std::fs::read_to_string(path).map_err(|e| {
+                    io::Error::new(e.kind(), format!("Failed to read seed file {path:?}: {e}"))
+                })?;
+                let seed = seed
+                    .trim()
+                    .parse()
+                    .map_err(|e| invalid(format!("{path:?}"), e))?;
+                Ok(Some(seed))
+            }
+            (None, None) => match std::env::var(SEED_ENV_VAR) {
+                Ok(seed) => {
+                    let seed = seed
+                        .trim()
+                        .parse()
+                        .map_err(|e| invalid(SEED_ENV_VAR.into(), e))?;
+                    Ok(Some(seed))
+                }
+                Err(_) => Ok(None),
+            },
+        }
+    }
+
+    /// Whether the seed is written in the config although it determines the offsets of `shift_time`
+    pub fn exposes_time_shift_seed(&self) -> bool {
+        self.seed.is_some()
+            && self
+                .steps
+                .iter()
+                .any(|s| matches!(s, StepConfig::ShiftTime { .. }))
+    }
+
+    /// Timezone of the output (UTC if not set)
+    pub fn timezone(&self) -> io::Result<Tz> {
+        parse_optional_timezone(&self.to_timezone)
+    }
+
+    /// Timezone of dates without UTC offset in the inputs (UTC if not set)
+    pub fn input_timezone(&self) -> io::Result<Tz> {
+        parse_optional_timezone(&self.assume_timezone)
+    }
+
+    /// Load all steps (e.g., reading mapping and key files)
+    ///
+    /// If `record_mapping` is set, pseudonymization steps record the replaced values
+    pub fn load_steps(&self, record_mapping: bool) -> io::Result<Vec<Step>> {
+        self.steps
+            .iter()
+            .map(|step| step.load(record_mapping))
+            .collect()
+    }
+}
+
+impl StepConfig {
+    fn load(&self, record_mapping: bool) -> io::Result<Step> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        Ok(match self {
+            StepConfig::OrderEvents(order) => Step::OrderEvents(order.clone()),
+            StepConfig::RenameActivities {
+                mapping,
+                drop_empty,
+            } => Step::RenameActivities(ActivityMapping::load(mapping, *drop_empty).map_err(
+                |e| io::Error::new(e.kind(), format!("Failed to load {mapping:?}: {e}")),
+            )?),
+            StepConfig::CollapseLifecycle => Step::CollapseLifecycle,
+            StepConfig::FilterEvents(filter) => Step::FilterEvents(filter.clone()),
+            StepConfig::FilterTraces(filter) => Step::FilterTraces(filter.clone()),
+            StepConfig::Sample { fraction, count } => match (fraction, count) {
+                (Some(fraction), None) if (0.0..=1.0).contains(fraction) => {
+                    Step::Sample(Sampling::Fraction(*fraction))
+                }
+                (Some(fraction), None) => {
+                    return Err(invalid(format!(
+                        "Invalid sample fraction {fraction} (expected a number between 0 and 1)"
+                    )))
+                }
+                (None, Some(count)) => Step::Sample(Sampling::Count(*count)),
+                _ => {
+                    return Err(invalid(
+                        "Sample steps need either a fraction or a count".to_string(),
+                    ))
+                }
+            },
+            StepConfig::KeepAttributes { log, trace, event } => {
+                let keep_list =
+                    |patterns: &Option<Vec<String>>| patterns.clone().map(KeepList::new);
+                Step::KeepAttributes {
+                    log: keep_list(log),
+                    trace: keep_list(trace),
+                    event: keep_list(event),
+                }
+            }
+            StepConfig::ShiftTime { max_shift } => {
+                Step::ShiftTime(timestamps::parse_duration(max_shift).map_err(invalid)?)
+            }
+            StepConfig::CoarsenTime { granularity, round } => Step::CoarsenTime {
+                granularity: *granularity,
+                round: *round,
+            },
+            StepConfig::Enrich { attributes } => Step::Enrich(attributes.clone()),
+            StepConfig::Pseudonymize {
+                trace_attrs,
+                event_attrs,
+                key_file,
+            } => {
+                if trace_attrs.is_empty() && event_attrs.is_empty() {
+                    return Err(invalid(
+                        "Pseudonymize steps need at least one trace or event attribute".to_string(),
+                    ));
+                }
+                let secret = pseudonymize::load_secret(key_file.as_deref())?;
+                let pseudonymizer =
+                    Pseudonymizer::new(&secret, trace_attrs.clone(), event_attrs.clone());
+                Step::Pseudonymize(if record_mapping {
+                    pseudonymizer.record_mapping()
+                } else {
+                    pseudonymizer
+                })
+            }
+            StepConfig::KAnonymity { k, mode } => Step::KAnonymity { k: *k, mode: *mode },
+        })
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use process_mining::event_log::{AttributeValue, Attributes, Event, Trace, XESEditableAttribute};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::filter::{activity, time_span};
+
+/// Number of hex characters of the SHA-256 hash used as variant hash
+const VARIANT_HASH_LENGTH: usize = 16;
+
+/// Derived attribute added to each event or trace
+///
+/// Durations are in seconds (like the `duration` of collapsed lifecycle pairs) and only added if the needed timestamps exist.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Enrichment {
+    /// Event attribute `event_index`: position of the event in its trace (starting at 0)
+    EventIndex,
+    /// Event attribute `time_since_previous`: seconds since the previous event (in trace order) with a timestamp
+    TimeSincePrevious,
+    /// Event attribute `time_since_start`: seconds since the earliest event of the trace
+    TimeSinceStart,
+    /// Trace attribute `case_duration`: seconds between the earliest and the latest event
+    CaseDuration,
+    /// Trace attribute `num_events`: number of events of the trace
+    NumEvents,
+    /// Trace attribute `variant_hash`: hash of the activity sequence, equal for traces of the same variant
+    VariantHash,
+}
+
+impl Enrichment {
+    /// Key of the created attribute
+    pub fn key(self) -> &'static str {
+        match self {
+            Enrichment::EventIndex => "event_index",
+            Enrichment::TimeSincePrevious => "time_since_previous",
+            Enrichment::TimeSinceStart => "time_since_start",
+            Enrichment::CaseDuration => "case_duration",
+            Enrichment::NumEvents => "num_events",
+            Enrichment::VariantHash => "variant_hash",
+        }
+    }
+
+    /// Whether the attribute is added to the trace (instead of to each event)
+    pub fn is_trace_attribute(self) -> bool {
+        matches!(
+            self,
+            Enrichment::CaseDuration | Enrichment::NumEvents | Enrichment::VariantHash
+        )
+    }
+
+    /// Default value of the global attribute declared in the log header
+    ///
+    /// Only attributes added to every trace (or event) are declared, i.e., not those derived from timestamps
+    pub fn global_default(self) -> Option<AttributeValue> {
+        match self {
+            Enrichment::EventIndex | Enrichment::NumEvents => Some(AttributeValue::Int(0)),
+            Enrichment::VariantHash => Some(AttributeValue::String(String::new())),
+            _ => None,
+        }
+    }
+
+    /// Event attributes from which the attribute is derived
+    pub fn required_event_attributes(self) -> Vec<&'static str> {
+        match self {
+            Enrichment::EventIndex | Enrichment::NumEvents => Vec::new(),
+            Enrichment::VariantHash => vec!["concept:name"],
+            _ => vec!["time:timestamp"],
+        }
+    }
+}
+
+/// Add the derived attributes to the trace and its events (replacing existing attributes with the same key)
+pub fn enrich(trace: &mut Trace, enrichments: &[Enrichment]) {
+    let span = time_span(trace);
+    for enrichment in enrichments {
+        let value = match enrichment {
+            Enrichment::CaseDuration => match span {
+                Some((first, last)) => AttributeValue::Float(seconds(first, last)),
+                None => continue,
+            },
+            Enrichment::NumEvents => AttributeValue::Int(trace.events.len() as i64),
+            Enrichment::VariantHash => AttributeValue::String(variant_hash(trace)),
+            _ => continue,
+        };
+        set_attribute(&mut trace.attributes, enrichment.key(), value);
+    }
+
+    let mut previous = None;
+    for (index, e) in trace.events.iter_mut().enumerate() {
+        let time = timestamp(e);
+        for enrichment in enrichments {
+            let value = match (enrichment, time, previous, span) {
+                (Enrichment::EventIndex, ..) => AttributeValue::Int(index as i64),
+                (Enrichment::TimeSincePrevious, Some(time), previous, _) => {
+                    AttributeValue::Float(previous.map_or(0.0, |p| seconds(p, time)))
+                }
+                (Enrichment::TimeSinceStart, Some(time), _, Some((first, _))) => {
+                    AttributeValue::Float(seconds(first, time))
+                }
+                _ => continue,
+            };
+            set_attribute(&mut e.attributes, enrichment.key(), value);
+        }
+        previous = time.or(previous);
+    }
+}
+
+/// Stable (hex) hash of the activity sequence of the trace
+fn variant_hash(trace: &Trace) -> String {
+    let mut hasher = Sha256::new();
+    for e in &trace.events {
+        hasher.update(activity(e).map_or("", |a| a.as_str()));
+        // Separator, so that, e.g., `ab`, `c` and `a`, `bc` have different hashes
+        hasher.update([0]);
+    }
+    let mut s: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    s.truncate(VARIANT_HASH_LENGTH);
+    s
+}
+
+fn timestamp(event: &Event) -> Option<DateTime<Utc>> {
+    event
+        .attributes
+        .get_by_key("time:timestamp")
+        .and_then(|a| a.value.try_as_date())
+        .copied()
+}
+
+fn seconds(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    (to - from).num_milliseconds() as f64 / 1000.0
+}
+
+fn set_attribute(attributes: &mut Attributes, key: &str, value: AttributeValue) {
+    attributes.remove_with_key(key);
+    attributes.add_to_attributes(key.to_string(), value);
+}
@@ -0,0 +1,87 @@
+use std::{fmt, io, path::PathBuf};
+
+use process_mining::event_log::import_xes::XESParseError;
+
+/// Errors aborting `log_strip`, each kind with its own exit code
+///
+/// Exit codes 1 (`validate` found issues) and 2 (invalid command line arguments) are used outside of these errors.
+/// Parse errors only keep their message, so that errors can be sent between threads (unlike
+/// [`XESParseError`]).
+#[derive(Debug)]
+pub enum StripError {
+    /// An input file could not be opened, e.g., as it does not exist (exit code 3)
+    InputMissing { path: PathBuf, error: io::Error },
+    /// An input could not be parsed, e.g., because of malformed XML or an invalid CSV row (exit code 4)
+    Parse { input: PathBuf, message: String },
+    /// An output file could not be written (exit code 5)
+    Write { path: PathBuf, error: io::Error },
+    /// Invalid configuration, e.g., an invalid pipeline, mapping or key file (exit code 6)
+    Config(String),
+    /// Some inputs of a batch failed (with the exit code of the first failed input)
+    Batch {
+        failed: usize,
+        total: usize,
+        exit_code: u8,
+    },
+}
+
+impl StripError {
+    pub fn parse(input: impl Into<PathBuf>, message: impl fmt::Display) -> Self {
+        StripError::Parse {
+            input: input.into(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Parse error of the XES parser (or of the CSV parser, which reports errors as [`XESParseError::IOError`])
+    pub fn from_xes(input: impl Into<PathBuf>, error: &XESParseError) -> Self {
+        Self::parse(input, xes_error_message(error))
+    }
+
+    pub fn write(path: impl Into<PathBuf>, error: io::Error) -> Self {
+        StripError::Write {
+            path: path.into(),
+            error,
+        }
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            StripError::InputMissing { .. } => 3,
+            StripError::Parse { .. } => 4,
+            StripError::Write { .. } => 5,
+            StripError::Config(_) => 6,
+            StripError::Batch { exit_code, .. } => *exit_code,
+        }
+    }
+}
+
+/// Message of a XES parse error (without the debug representation of wrapped IO errors)
+pub fn xes_error_message(error: &XESParseError) -> String {
+    match error {
+        XESParseError::IOError(e) => e.to_string(),
+        // e.g., dates which can not be converted while reading (see `xes_dates::AssumeTimezoneReader`)
+        XESParseError::XMLParsingError(quick_xml::Error::Io(e)) => e.to_string(),
+        e => e.to_string(),
+    }
+}
+
+impl fmt::Display for StripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StripError::InputMissing { path, error } => {
+                write!(f, "Failed to open input {path:?}: {error}")
+            }
+            StripError::Parse { input, message } => {
+                write!(f, "Failed to parse input {input:?}: {message}")
+            }
+            StripError::Write { path, error } => write!(f, "Failed to write {path:?}: {error}"),
+            StripError::Config(message) => write!(f, "{message}"),
+            StripError::Batch { failed, total, .. } => {
+                write!(f, "{failed} of {total} inputs failed")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StripError {}
@@ -0,0 +1,675 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Read, Write},
+};
+
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use process_mining::event_log::{
+    import_xes::XESParseError, stream_xes::XESOuterLogData, AttributeValue, Attributes, Event,
+    Trace, XESEditableAttribute,
+};
+
+use crate::{
+    header,
+    input::SkippedRecords,
+    pipeline::Step,
+    timestamps::{format_date, parse_date_in, parse_date_with_format},
+};
+
+/// Prefix for trace attribute columns (e.g., `case:concept:name` for the case ID)
+pub const TRACE_ATTRIBUTE_PREFIX: &str = "case:";
+
+/// Options for exporting traces as flat event CSV
+#[derive(clap::Args, Debug, Clone, Default)]
+#[command(next_help_heading = "CSV Export (if the output ends with .csv)")]
+pub struct CsvExportArgs {
+    /// Trace attributes to include as columns (prefixed with `case:`)
+    ///
+    /// If not specified, all kept trace attributes are included (if they are not selected with wildcards;
+    /// otherwise, those of the log header and the first trace)
+    #[clap(long = "csv-trace-attr", value_name = "KEY")]
+    pub csv_trace_attrs: Vec<String>,
+    /// Event attributes to include as columns
+    ///
+    /// If not specified, all kept event attributes are included (if they are not selected with wildcards;
+    /// otherwise, those of the log header and the events of the first trace)
+    #[clap(long = "csv-event-attr", value_name = "KEY")]
+    pub csv_event_attrs: Vec<String>,
+}
+
+/// String representation of an attribute value for CSV (empty for lists, containers and missing values)
+///
+/// Dates are written in the given timezone
+pub fn csv_value(value: Option<&AttributeValue>, tz: Tz) -> String {
+    match value {
+        Some(AttributeValue::String(s)) => s.clone(),
+        Some(AttributeValue::Date(d)) => format_date(d, tz),
+        Some(AttributeValue::Int(i)) => i.to_string(),
+        Some(AttributeValue::Float(f)) => f.to_string(),
+        Some(AttributeValue::Boolean(b)) => b.to_string(),
+        Some(AttributeValue::ID(id)) => id.to_string(),
+        _ => String::new(),
+    }
+}
+
+impl CsvExportArgs {
+    /// Columns of the export: the specified attributes, or else all attributes which can be kept by the steps
+    /// (see [`header::output_trace_keys`])
+    pub fn columns(&self, steps: &[Step]) -> CsvColumns {
+        let columns = |specified: &Vec<String>, kept: Option<Vec<String>>| Columns {
+            keys: if specified.is_empty() {
+                kept
+            } else {
+                Some(specified.clone())
+            },
+            inferred: false,
+        };
+        CsvColumns {
+            trace: columns(&self.csv_trace_attrs, header::output_trace_keys(steps)),
+            event: columns(&self.csv_event_attrs, header::output_event_keys(steps)),
+        }
+    }
+}
+
+/// Attribute keys of the columns of one level (trace or event)
+#[derive(Debug, Clone, Default)]
+struct Columns {
+    /// None: not known before exporting (see [`CsvColumns::infer`])
+    keys: Option<Vec<String>>,
+    /// Whether the keys were inferred from the log header and the first trace, so that later attributes may be missing
+    inferred: bool,
+}
+
+impl Columns {
+    fn infer<'a>(&mut self, globals: &'a Attributes, first: impl Iterator<Item = &'a Attributes>) {
+        if self.keys.is_some() {
+            return;
+        }
+        let mut keys: Vec<String> = Vec::new();
+        for a in globals.iter().chain(first.flatten()) {
+            if !keys.contains(&a.key) {
+                keys.push(a.key.clone());
+            }
+        }
+        self.keys = Some(keys);
+        self.inferred = true;
+    }
+}
+
+/// Columns of a CSV export, shared by all files of a split export
+#[derive(Debug, Clone, Default)]
+pub struct CsvColumns {
+    trace: Columns,
+    event: Columns,
+}
+
+impl CsvColumns {
+    /// Infer columns not known before exporting from the global attributes and the attributes of the first trace
+    pub fn infer(&mut self, log_data: &XESOuterLogData, first: Option<&Trace>) {
+        self.trace.infer(
+            &log_data.global_trace_attrs,
+            first.map(|t| &t.attributes).into_iter(),
+        );
+        self.event.infer(
+            &log_data.global_event_attrs,
+            first
+                .into_iter()
+                .flat_map(|t| t.events.iter().map(|e| &e.attributes)),
+        );
+    }
+}
+
+/// Streaming CSV writer with one row per event
+///
+/// Each row contains the trace attributes of the columns (i.e., repeated for every event of a trace) followed by the event attributes.
+/// Traces without events are not included.
+/// If columns were inferred, attributes which are not exported are reported (once per level).
+pub struct CsvTraceWriter<W: Write> {
+    writer: csv::Writer<W>,
+    trace_keys: Vec<String>,
+    event_keys: Vec<String>,
+    /// Known keys of inferred columns, for detecting attributes which are not exported (None: not inferred or already reported)
+    unknown_trace_keys: Option<HashSet<String>>,
+    unknown_event_keys: Option<HashSet<String>>,
+    tz: Tz,
+}
+
+impl<W: Write> CsvTraceWriter<W> {
+    /// Write the header row (the columns have to be inferred before, see [`CsvColumns::infer`])
+    pub fn new(writer: W, columns: &CsvColumns, tz: Tz) -> csv::Result<Self> {
+        let mut writer = Self::resume(writer, columns, tz);
+        writer.writer.write_record(
+            writer
+                .trace_keys
+                .iter()
+                .map(|k| format!("{TRACE_ATTRIBUTE_PREFIX}{k}"))
+                .chain(writer.event_keys.iter().cloned()),
+        )?;
+        Ok(writer)
+    }
+
+    /// Continue writing rows to a writer appending to the output of a previous writer (see [`CsvTraceWriter::suspend`])
+    pub fn resume(writer: W, columns: &CsvColumns, tz: Tz) -> Self {
+        let keys = |c: &Columns| c.keys.clone().expect("CSV columns are inferred");
+        let known = |c: &Columns| c.inferred.then(|| keys(c).into_iter().collect());
+        Self {
+            writer: csv::Writer::from_writer(writer),
+            trace_keys: keys(&columns.trace),
+            event_keys: keys(&columns.event),
+            unknown_trace_keys: known(&columns.trace),
+            unknown_event_keys: known(&columns.event),
+            tz,
+        }
+    }
+
+    pub fn write_trace(&mut self, t: &Trace) -> csv::Result<()> {
+        report_unknown_keys(&mut self.unknown_trace_keys, &t.attributes, "trace");
+        let trace_values: Vec<String> = self
+            .trace_keys
+            .iter()
+            .map(|k| csv_value(t.attributes.get_by_key(k).map(|a| &a.value), self.tz))
+            .collect();
+        for e in &t.events {
+            report_unknown_keys(&mut self.unknown_event_keys, &e.attributes, "event");
+            self.writer.write_record(
+                trace_values.iter().cloned().chain(
+                    self.event_keys
+                        .iter()
+                        .map(|k| csv_value(e.attributes.get_by_key(k).map(|a| &a.value), self.tz)),
+                ),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Flush the written rows, returning the inner writer
+    pub fn finish(self) -> io::Result<W> {
+        self.writer.into_inner().map_err(|e| e.into_error())
+    }
+
+    /// Flush the written rows, returning the inner writer and the columns to resume writing with
+    /// (where levels with already reported attributes are no longer reported)
+    pub fn suspend(self) -> io::Result<(W, CsvColumns)> {
+        let columns = |keys: Vec<String>, unknown: Option<HashSet<String>>| Columns {
+            keys: Some(keys),
+            inferred: unknown.is_some(),
+        };
+        let columns = CsvColumns {
+            trace: columns(self.trace_keys, self.unknown_trace_keys),
+            event: columns(self.event_keys, self.unknown_event_keys),
+        };
+        Ok((
+            self.writer.into_inner().map_err(|e| e.into_error())?,
+            columns,
+        ))
+    }
+}
+
+/// Warn (once) about an attribute which is not a column
+fn report_unknown_keys(known: &mut Option<HashSet<String>>, attributes: &Attributes, level: &str) {
+    let Some(keys) = known else {
+        return;
+    };
+    if let Some(a) = attributes.iter().find(|a| !keys.contains(&a.key)) {
+        eprintln!(
+            "Warning: The {level} attribute {:?} is not exported, as the CSV columns were determined from the log header and the first trace \
+            (use --csv-{level}-attr to specify the columns)",
+            a.key
+        );
+        *known = None;
+    }
+}
+
+/// Export a trace stream as CSV (see [`CsvTraceWriter`])
+pub fn export_csv_trace_stream<I, W>(
+    traces: I,
+    log_data: &XESOuterLogData,
+    writer: W,
+    columns: &CsvColumns,
+    tz: Tz,
+) -> io::Result<W>
+where
+    I: Iterator<Item = Trace>,
+    W: Write,
+{
+    let mut traces = traces.peekable();
+    let mut columns = columns.clone();
+    columns.infer(log_data, traces.peek());
+    let mut writer = CsvTraceWriter::new(writer, &columns, tz)?;
+    for t in traces {
+        writer.write_trace(&t)?;
+    }
+    writer.finish()
+}
+
+/// Type of an attribute imported from a CSV column
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvAttributeType {
+    String,
+    Int,
+    Float,
+    Boolean,
+    Date,
+}
+
+/// CSV column imported as attribute (columns prefixed with `case:` are imported as trace attributes)
+#[derive(Debug, Clone)]
+pub struct CsvAttribute {
+    pub column: String,
+    pub attribute_type: CsvAttributeType,
+}
+
+/// Parse a CSV attribute column as `COLUMN` or `COLUMN=TYPE`
+pub fn parse_csv_attribute(s: &str) -> Result<CsvAttribute, String> {
+    let (column, attribute_type) = match s.rsplit_once('=') {
+        Some((column, attribute_type)) => (
+            column,
+            CsvAttributeType::from_str(attribute_type, true)
+                .map_err(|_| format!("Invalid attribute type {attribute_type:?}"))?,
+        ),
+        None => (s, CsvAttributeType::String),
+    };
+    Ok(CsvAttribute {
+        column: column.to_string(),
+        attribute_type,
+    })
+}
+
+/// Options for importing inputs ending with .csv (one row per event)
+#[derive(clap::Args, Debug, Clone)]
+#[command(next_help_heading = "CSV Import (if an input ends with .csv)")]
+pub struct CsvImportArgs {
+    /// Column containing the case ID (imported as trace `concept:name`)
+    #[clap(long, value_name = "COLUMN", default_value = "case:concept:name")]
+    pub csv_case_column: String,
+    /// Column containing the activity (imported as event `concept:name`)
+    #[clap(long, value_name = "COLUMN", default_value = "concept:name")]
+    pub csv_activity_column: String,
+    /// Column containing the timestamp (imported as event `time:timestamp`)
+    #[clap(long, value_name = "COLUMN", default_value = "time:timestamp")]
+    pub csv_timestamp_column: String,
+    /// Format of the timestamps and other dates (e.g., `%d.%m.%Y %H:%M:%S`), interpreted in `--assume-timezone` if no offset (`%z`) is included
+    ///
+    /// If not specified, RFC 3339 dates (e.g., `2023-01-01T12:00:00+01:00`) are expected
+    #[clap(long, value_name = "FORMAT")]
+    pub csv_timestamp_format: Option<String>,
+    /// Additional column to import as attribute, with an optional type (string, int, float, boolean or date; e.g., `cost=float`)
+    ///
+    /// Columns prefixed with `case:` are imported as trace attributes.
+    /// If not specified, all remaining columns are imported as string attributes
+    #[clap(long = "csv-attr", value_name = "COLUMN[=TYPE]", value_parser = parse_csv_attribute)]
+    pub csv_attrs: Vec<CsvAttribute>,
+    /// Field delimiter
+    #[clap(long, value_name = "CHAR", default_value_t = ',')]
+    pub csv_delimiter: char,
+    /// Expect all rows of a case to be consecutive, so that traces can be streamed (instead of grouping all rows in memory)
+    #[clap(long)]
+    pub csv_sorted: bool,
+    /// Import rows with a missing or unparseable timestamp (without `time:timestamp`, or with the cell as string value) instead of rejecting them
+    #[clap(skip)]
+    pub lenient_timestamps: bool,
+}
+
+/// Column indices of the mapped attributes
+struct ColumnMapping {
+    case_id: usize,
+    activity: usize,
+    timestamp: usize,
+    /// Attribute key, column index, type and if the attribute is a trace attribute
+    attributes: Vec<(String, usize, CsvAttributeType, bool)>,
+    date_format: Option<String>,
+    timezone: Tz,
+    lenient_timestamps: bool,
+}
+
+impl ColumnMapping {
+    fn new(header: &csv::StringRecord, args: &CsvImportArgs, timezone: Tz) -> Result<Self, String> {
+        let index = |column: &str| {
+            header
+                .iter()
+                .position(|h| h == column)
+                .ok_or_else(|| format!("Column {column:?} not found in CSV header"))
+        };
+        let case_id = index(&args.csv_case_column)?;
+        let activity = index(&args.csv_activity_column)?;
+        let timestamp = index(&args.csv_timestamp_column)?;
+        let columns: Vec<CsvAttribute> = if args.csv_attrs.is_empty() {
+            header
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| ![case_id, activity, timestamp].contains(i))
+                .map(|(_, column)| CsvAttribute {
+                    column: column.to_string(),
+                    attribute_type: CsvAttributeType::String,
+                })
+                .collect()
+        } else {
+            args.csv_attrs.clone()
+        };
+        let attributes = columns
+            .into_iter()
+            .map(|a| {
+                let i = index(&a.column)?;
+                Ok(match a.column.strip_prefix(TRACE_ATTRIBUTE_PREFIX) {
+                    Some(key) => (key.to_string(), i, a.attribute_type, true),
+                    None => (a.column, i, a.attribute_type, false),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            case_id,
+            activity,
+            timestamp,
+            attributes,
+            date_format: args.csv_timestamp_format.clone(),
+            timezone,
+            lenient_timestamps: args.lenient_timestamps,
+        })
+    }
+
+    fn parse_date(&self, s: &str) -> Result<AttributeValue, String> {
+        match &self.date_format {
+            Some(format) => parse_date_with_format(s, format, self.timezone),
+            None => parse_date_in(s, self.timezone),
+        }
+        .map(AttributeValue::Date)
+    }
+
+    fn parse_value(
+        &self,
+        s: &str,
+        attribute_type: CsvAttributeType,
+    ) -> Result<AttributeValue, String> {
+        match attribute_type {
+            CsvAttributeType::String => Ok(AttributeValue::String(s.to_string())),
+            CsvAttributeType::Int => s
+                .parse()
+                .map(AttributeValue::Int)
+                .map_err(|_| format!("Invalid int {s:?}")),
+            CsvAttributeType::Float => s
+                .parse()
+                .map(AttributeValue::Float)
+                .map_err(|_| format!("Invalid float {s:?}")),
+            CsvAttributeType::Boolean => s
+                .to_lowercase()
+                .parse()
+                .map(AttributeValue::Boolean)
+                .map_err(|_| format!("Invalid boolean {s:?}")),
+            CsvAttributeType::Date => self.parse_date(s),
+        }
+    }
+
+    /// Convert a row to its case ID, trace attributes and event
+    ///
+    /// Empty cells of additional attributes are skipped
+    fn parse_row(&self, row: &csv::StringRecord) -> Result<(String, Attributes, Event), String> {
+        let case_id = &row[self.case_id];
+        if case_id.is_empty() {
+            return Err("Missing case ID".to_string());
+        }
+        let mut event = Event::new(row[self.activity].to_string());
+        let timestamp = &row[self.timestamp];
+        match self.parse_date(timestamp) {
+            Ok(date) => event
+                .attributes
+                .add_to_attributes("time:timestamp".to_string(), date),
+            Err(_) if self.lenient_timestamps && timestamp.is_empty() => {}
+            Err(_) if self.lenient_timestamps => event.attributes.add_to_attributes(
+                "time:timestamp".to_string(),
+                AttributeValue::String(timestamp.to_string()),
+            ),
+            Err(e) => return Err(e),
+        }
+        let mut trace_attributes = Attributes::new();
+        trace_attributes.add_to_attributes(
+            "concept:name".to_string(),
+            AttributeValue::String(case_id.to_string()),
+        );
+        for (key, i, attribute_type, is_trace_attribute) in &self.attributes {
+            let value = &row[*i];
+            if value.is_empty() {
+                continue;
+            }
+            let value = self.parse_value(value, *attribute_type)?;
+            if *is_trace_attribute {
+                trace_attributes.add_to_attributes(key.clone(), value);
+            } else {
+                event.attributes.add_to_attributes(key.clone(), value);
+            }
+        }
+        Ok((case_id.to_string(), trace_attributes, event))
+    }
+}
+
+/// Trace stream over the rows of a CSV file, grouped into traces by their case ID
+///
+/// Trace attributes are taken from the first row of each case.
+/// Unless the rows are sorted by case (see [`CsvImportArgs::csv_sorted`]), all rows are read and grouped
+/// before the first trace is emitted, preserving the order of the first occurrence of each case.
+pub struct CsvTraceStream {
+    rows: csv::StringRecordsIntoIter<Box<dyn Read>>,
+    mapping: ColumnMapping,
+    sorted: bool,
+    /// Trace currently being collected (only when sorted)
+    current: Option<(String, Trace)>,
+    /// Case IDs of already emitted traces (only when sorted, to detect non-consecutive rows)
+    finished_case_ids: HashSet<String>,
+    /// All traces (only when not sorted, after grouping)
+    grouped: Option<std::vec::IntoIter<Trace>>,
+    /// Skip invalid rows (instead of ending the stream with an error)
+    skip_invalid: bool,
+    /// Skipped invalid rows (counted as events)
+    pub skipped: SkippedRecords,
+    /// Error encountered while reading or parsing a row (ends the stream)
+    pub error: Option<XESParseError>,
+}
+
+impl CsvTraceStream {
+    /// Read the header of a CSV input, whose dates without UTC offset are interpreted in `timezone`
+    pub fn open(
+        reader: Box<dyn Read>,
+        args: &CsvImportArgs,
+        timezone: Tz,
+        skip_invalid: bool,
+    ) -> Result<Self, XESParseError> {
+        let delimiter = u8::try_from(args.csv_delimiter)
+            .map_err(|_| io::Error::other("CSV delimiter must be an ASCII character"))?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(reader);
+        let header = reader.headers().map_err(io::Error::from)?;
+        let mapping = ColumnMapping::new(header, args, timezone).map_err(io::Error::other)?;
+        Ok(Self {
+            rows: reader.into_records(),
+            mapping,
+            sorted: args.csv_sorted,
+            current: None,
+            finished_case_ids: HashSet::new(),
+            grouped: None,
+            skip_invalid,
+            skipped: SkippedRecords::default(),
+            error: None,
+        })
+    }
+
+    /// Read and parse the next row (storing errors and returning `None` on failure)
+    ///
+    /// Invalid rows are skipped if `skip_invalid` is set (read errors still end the stream)
+    fn next_row(&mut self) -> Option<(String, Attributes, Event)> {
+        loop {
+            let row = match self.rows.next()? {
+                Ok(row) => row,
+                Err(e) if self.skip_invalid && !e.is_io_error() => {
+                    self.skipped.skip_event(e);
+                    continue;
+                }
+                Err(e) => {
+                    self.error = Some(io::Error::from(e).into());
+                    return None;
+                }
+            };
+            match self.mapping.parse_row(&row) {
+                Ok(parsed) => return Some(parsed),
+                Err(e) => {
+                    let line = row.position().map(|p| p.line()).unwrap_or_default();
+                    let message = format!("{e} in CSV line {line}");
+                    if !self.skip_invalid {
+                        self.error = Some(io::Error::other(message).into());
+                        return None;
+                    }
+                    self.skipped.skip_event(message);
+                }
+            }
+        }
+    }
+
+    /// Read all rows and group them into traces
+    fn group_all(&mut self) -> Vec<Trace> {
+        let mut traces: Vec<Trace> = Vec::new();
+        let mut trace_indices: HashMap<String, usize> = HashMap::new();
+        while let Some((case_id, attributes, event)) = self.next_row() {
+            let i = *trace_indices.entry(case_id).or_insert_with(|| {
+                traces.push(Trace {
+                    attributes,
+                    events: Vec::new(),
+                });
+                traces.len() - 1
+            });
+            traces[i].events.push(event);
+        }
+        traces
+    }
+}
+
+impl Iterator for CsvTraceStream {
+    type Item = Trace;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None;
+        }
+        if !self.sorted {
+            if self.grouped.is_none() {
+                self.grouped = Some(self.group_all().into_iter());
+            }
+            return self.grouped.as_mut()?.next();
+        }
+        while let Some((case_id, attributes, event)) = self.next_row() {
+            match &mut self.current {
+                Some((current_id, trace)) if *current_id == case_id => trace.events.push(event),
+                _ => {
+                    if !self.finished_case_ids.insert(case_id.clone()) {
+                        self.error = Some(
+                            io::Error::other(format!(
+                                "Rows of case {case_id:?} are not consecutive (CSV is not sorted by case)"
+                            ))
+                            .into(),
+                        );
+                        return None;
+                    }
+                    let trace = Trace {
+                        attributes,
+                        events: vec![event],
+                    };
+                    if let Some((_, finished)) = self.current.replace((case_id, trace)) {
+                        return Some(finished);
+                    }
+                }
+            }
+        }
+        self.current.take().map(|(_, trace)| trace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        csv: CsvImportArgs,
+    }
+
+    fn open(rows: &str, args: &[&str]) -> CsvTraceStream {
+        let cli = Cli::parse_from(std::iter::once("test").chain(args.iter().copied()));
+        let csv = format!("case:concept:name,concept:name,time:timestamp\n{rows}");
+        CsvTraceStream::open(
+            Box::new(Cursor::new(csv.into_bytes())),
+            &cli.csv,
+            Tz::UTC,
+            false,
+        )
+        .unwrap()
+    }
+
+    /// Case ID and activities of each trace
+    fn traces(stream: &mut CsvTraceStream) -> Vec<(String, Vec<String>)> {
+        stream
+            .map(|t| {
+                let case_id = t.attributes.get_by_key("concept:name").unwrap();
+                let activities = t
+                    .events
+                    .iter()
+                    .map(
+                        |e| match &e.attributes.get_by_key("concept:name").unwrap().value {
+                            AttributeValue::String(a) => a.clone(),
+                            v => panic!("Unexpected activity {v:?}"),
+                        },
+                    )
+                    .collect();
+                (case_id.value.try_as_string().unwrap().clone(), activities)
+            })
+            .collect()
+    }
+
+    fn expected(case_id: &str, activities: &[&str]) -> (String, Vec<String>) {
+        (
+            case_id.to_string(),
+            activities.iter().map(|a| a.to_string()).collect(),
+        )
+    }
+
+    const UNSORTED: &str = "c2,A,2023-01-01T10:00:00Z
+c1,B,2023-01-01T11:00:00Z
+c2,C,2023-01-01T12:00:00Z
+c1,D,2023-01-01T13:00:00Z
+";
+
+    #[test]
+    fn group_unsorted_rows() {
+        let mut stream = open(UNSORTED, &[]);
+        assert_eq!(
+            traces(&mut stream),
+            vec![expected("c2", &["A", "C"]), expected("c1", &["B", "D"])]
+        );
+        assert!(stream.error.is_none());
+    }
+
+    #[test]
+    fn stream_sorted_rows() {
+        let rows = "c2,A,2023-01-01T10:00:00Z
+c2,C,2023-01-01T12:00:00Z
+c1,AC,2023-01-01T11:00:00Z
+";
+        let mut stream = open(rows, &["--csv-sorted"]);
+        assert_eq!(
+            traces(&mut stream),
+            vec![expected("c2", &["A", "C"]), expected("c1", &["AC"])]
+        );
+        assert!(stream.error.is_none());
+    }
+
+    #[test]
+    fn reject_unsorted_rows_when_sorted() {
+        let mut stream = open(UNSORTED, &["--csv-sorted"]);
+        // Only c2 is emitted before its second row shows that the rows are not sorted
+        assert_eq!(traces(&mut stream).len(), 1);
+        assert!(stream.error.is_some());
+    }
+}
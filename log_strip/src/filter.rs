@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use process_mining::event_log::{Event, Trace, XESEditableAttribute};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    attributes::glob_match,
+    timestamps::{self, parse_date},
+};
+
+/// How the time span of a trace (i.e., from its first to its last event) has to relate to the time window
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowMode {
+    /// First event lies in the time window
+    Starts,
+    /// Last event lies in the time window
+    Ends,
+    /// All events lie in the time window
+    Contained,
+    /// Any part of the trace overlaps with the time window
+    #[default]
+    Intersects,
+}
+
+/// Filters applied to whole traces (i.e., traces are either kept completely or dropped)
+#[derive(clap::Args, Debug, Clone, Default, Serialize, Deserialize)]
+#[command(next_help_heading = "Trace Filters")]
+#[serde(default, deny_unknown_fields)]
+pub struct TraceFilter {
+    /// Only keep traces containing at least one of these activities
+    #[clap(long = "with-activity", value_name = "ACTIVITY")]
+    pub with_activities: Vec<String>,
+    /// Drop traces containing any of these activities
+    #[clap(long = "without-activity", value_name = "ACTIVITY")]
+    pub without_activities: Vec<String>,
+    /// Only keep traces with at least this many events
+    #[clap(long, value_name = "N")]
+    pub min_events: Option<usize>,
+    /// Only keep traces with at most this many events
+    #[clap(long, value_name = "N")]
+    pub max_events: Option<usize>,
+    /// Start of the time window (e.g., `2023-01-01` or `2023-01-01T12:00:00+01:00`)
+    #[clap(long, value_name = "DATE", value_parser = parse_date)]
+    #[serde(deserialize_with = "timestamps::deserialize_optional_date")]
+    pub window_start: Option<DateTime<Utc>>,
+    /// End of the time window (exclusive)
+    #[clap(long, value_name = "DATE", value_parser = parse_date)]
+    #[serde(deserialize_with = "timestamps::deserialize_optional_date")]
+    pub window_end: Option<DateTime<Utc>>,
+    /// How traces have to relate to the time window to be kept
+    #[clap(long, value_enum, default_value_t = WindowMode::Intersects)]
+    pub window_mode: WindowMode,
+}
+
+impl TraceFilter {
+    pub fn is_active(&self) -> bool {
+        !self.with_activities.is_empty()
+            || !self.without_activities.is_empty()
+            || self.min_events.is_some()
+            || self.max_events.is_some()
+            || self.window_start.is_some()
+            || self.window_end.is_some()
+    }
+
+    pub fn required_event_attributes(&self) -> Vec<&'static str> {
+        let mut keys = Vec::new();
+        if !self.with_activities.is_empty() || !self.without_activities.is_empty() {
+            keys.push("concept:name");
+        }
+        if self.window_start.is_some() || self.window_end.is_some() {
+            keys.push("time:timestamp");
+        }
+        keys
+    }
+
+    /// Check if the trace should be kept
+    pub fn matches(&self, trace: &Trace) -> bool {
+        let num_events = trace.events.len();
+        if self.min_events.is_some_and(|min| num_events < min)
+            || self.max_events.is_some_and(|max| num_events > max)
+        {
+            return false;
+        }
+        if !self.with_activities.is_empty()
+            && !trace
+                .events
+                .iter()
+                .any(|e| activity(e).is_some_and(|a| self.with_activities.contains(a)))
+        {
+            return false;
+        }
+        if trace
+            .events
+            .iter()
+            .any(|e| activity(e).is_some_and(|a| self.without_activities.contains(a)))
+        {
+            return false;
+        }
+        if self.window_start.is_some() || self.window_end.is_some() {
+            // Traces without any timestamps are never part of a time window
+            let Some((first, last)) = time_span(trace) else {
+                return false;
+            };
+            let in_window = |d: DateTime<Utc>| {
+                self.window_start.is_none_or(|start| d >= start)
+                    && self.window_end.is_none_or(|end| d < end)
+            };
+            let keep = match self.window_mode {
+                WindowMode::Starts => in_window(first),
+                WindowMode::Ends => in_window(last),
+                WindowMode::Contained => in_window(first) && in_window(last),
+                WindowMode::Intersects => {
+                    self.window_start.is_none_or(|start| last >= start)
+                        && self.window_end.is_none_or(|end| first < end)
+                }
+            };
+            if !keep {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Filters applied to single events (i.e., traces are kept, but without the removed events)
+#[derive(clap::Args, Debug, Clone, Default, Serialize, Deserialize)]
+#[command(next_help_heading = "Event Filters")]
+#[serde(default, deny_unknown_fields)]
+pub struct EventFilter {
+    /// Remove events with an activity matching this pattern (glob patterns like `SYS_*` are supported)
+    #[clap(long = "drop-activity", value_name = "PATTERN")]
+    pub drop_activities: Vec<String>,
+    /// Only keep events with an activity matching this pattern (glob patterns like `SYS_*` are supported)
+    #[clap(long = "keep-activity", value_name = "PATTERN")]
+    pub keep_activities: Vec<String>,
+    /// Drop traces which have no events left after removing events
+    #[clap(long)]
+    pub drop_empty_traces: bool,
+}
+
+impl EventFilter {
+    pub fn is_active(&self) -> bool {
+        !self.drop_activities.is_empty() || !self.keep_activities.is_empty()
+    }
+
+    /// Check if the event should be kept
+    pub fn matches(&self, event: &Event) -> bool {
+        let matches_any = |patterns: &[String]| {
+            activity(event).is_some_and(|a| patterns.iter().any(|p| glob_match(p, a)))
+        };
+        (self.keep_activities.is_empty() || matches_any(&self.keep_activities))
+            && !matches_any(&self.drop_activities)
+    }
+
+    /// Remove all events not matching the filter from the trace
+    ///
+    /// Returns `false` if the trace became empty and should be dropped (only with `--drop-empty-traces`)
+    pub fn apply(&self, trace: &mut Trace) -> bool {
+        if !self.is_active() || trace.events.is_empty() {
+            return true;
+        }
+        trace.events.retain(|e| self.matches(e));
+        !(self.drop_empty_traces && trace.events.is_empty())
+    }
+}
+
+pub fn activity(event: &Event) -> Option<&String> {
+    event
+        .attributes
+        .get_by_key("concept:name")
+        .and_then(|a| a.value.try_as_string())
+}
+
+/// Earliest and latest event timestamp of the trace (if any)
+pub fn time_span(trace: &Trace) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    trace
+        .events
+        .iter()
+        .filter_map(|e| {
+            e.attributes
+                .get_by_key("time:timestamp")
+                .and_then(|a| a.value.try_as_date())
+                .copied()
+        })
+        .fold(None, |span, d| match span {
+            None => Some((d, d)),
+            Some((first, last)) => Some((first.min(d), last.max(d))),
+        })
+}
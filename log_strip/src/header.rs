@@ -0,0 +1,528 @@
+use clap::ValueEnum;
+use process_mining::event_log::{
+    event_log_struct::EventLogExtension, stream_xes::XESOuterLogData, AttributeValue, Attributes,
+    XESEditableAttribute,
+};
+
+use crate::{attributes::KeepList, pipeline::Step};
+
+/// Standard XES extensions (name, prefix and URI), declared for used prefixes if missing
+const STANDARD_EXTENSIONS: [(&str, &str, &str); 8] = [
+    (
+        "Concept",
+        "concept",
+        "http://www.xes-standard.org/concept.xesext",
+    ),
+    ("Time", "time", "http://www.xes-standard.org/time.xesext"),
+    (
+        "Lifecycle",
+        "lifecycle",
+        "http://www.xes-standard.org/lifecycle.xesext",
+    ),
+    (
+        "Organizational",
+        "org",
+        "http://www.xes-standard.org/org.xesext",
+    ),
+    ("Cost", "cost", "http://www.xes-standard.org/cost.xesext"),
+    (
+        "Identity",
+        "identity",
+        "http://www.xes-standard.org/identity.xesext",
+    ),
+    (
+        "Semantic",
+        "semantic",
+        "http://www.xes-standard.org/semantic.xesext",
+    ),
+    ("Micro", "micro", "http://www.xes-standard.org/micro.xesext"),
+];
+
+/// Which declarations of the input log header are written
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderMode {
+    /// Keep the declarations of the input(s) unchanged
+    Keep,
+    /// Only keep declarations consistent with the kept attributes (and declare standard extensions of kept attributes)
+    Consistent,
+    /// Remove all declarations
+    Drop,
+}
+
+/// Options for the log header (extensions, global attributes, classifiers and log attributes)
+#[derive(clap::Args, Debug, Clone)]
+#[command(next_help_heading = "Log Header")]
+pub struct HeaderArgs {
+    /// Extension declarations to write (`consistent`: extensions of prefixes which kept attributes can have)
+    #[clap(long = "header-extensions", value_name = "MODE", value_enum, default_value_t = HeaderMode::Consistent)]
+    pub extensions: HeaderMode,
+    /// Global trace and event attributes to write (`consistent`: globals of kept attributes, with pseudonymized defaults, and of attributes added to every trace/event by the steps)
+    #[clap(long = "header-globals", value_name = "MODE", value_enum, default_value_t = HeaderMode::Consistent)]
+    pub globals: HeaderMode,
+    /// Classifiers to write (`consistent`: classifiers whose keys are all kept event attributes)
+    #[clap(long = "header-classifiers", value_name = "MODE", value_enum, default_value_t = HeaderMode::Consistent)]
+    pub classifiers: HeaderMode,
+    /// Set a (string) log attribute, e.g., `concept:name=Stripped log` (added after selecting the kept log attributes)
+    #[clap(long = "set-log-attr", value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub set_log_attrs: Vec<(String, String)>,
+}
+
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Invalid attribute {s:?} (expected KEY=VALUE)")),
+    }
+}
+
+/// Attribute keys of one level (log, trace or event) kept by all attribute selection steps
+struct KeptKeys<'a> {
+    keep_lists: Vec<&'a KeepList>,
+}
+
+impl<'a> KeptKeys<'a> {
+    fn new(steps: &'a [Step], level: impl Fn(&'a Step) -> Option<&'a KeepList>) -> Self {
+        Self {
+            keep_lists: steps.iter().filter_map(level).collect(),
+        }
+    }
+
+    fn keeps(&self, key: &str) -> bool {
+        self.keep_lists.iter().all(|k| k.matches(key))
+    }
+
+    fn may_keep_prefix(&self, prefix: &str) -> bool {
+        self.keep_lists.iter().all(|k| k.may_match_prefix(prefix))
+    }
+
+    /// Exact keys which are kept (if present)
+    fn exact_keys(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.keep_lists
+            .iter()
+            .flat_map(|k| k.exact_keys())
+            .filter(|key| self.keeps(key))
+    }
+}
+
+/// Keys of all trace attributes which can be in the output, if they can be determined from the steps (see [`output_keys`])
+pub fn output_trace_keys(steps: &[Step]) -> Option<Vec<String>> {
+    output_keys(
+        steps,
+        |step| match step {
+            Step::KeepAttributes { trace, .. } => trace.as_ref(),
+            _ => None,
+        },
+        Step::created_trace_attributes,
+    )
+}
+
+/// Keys of all event attributes which can be in the output, if they can be determined from the steps (see [`output_keys`])
+pub fn output_event_keys(steps: &[Step]) -> Option<Vec<String>> {
+    output_keys(
+        steps,
+        |step| match step {
+            Step::KeepAttributes { event, .. } => event.as_ref(),
+            _ => None,
+        },
+        Step::created_event_attributes,
+    )
+}
+
+/// Keys of all attributes of one level which can be in the output (in the order of the patterns)
+///
+/// They can only be determined if some attribute selection step has no wildcard patterns.
+/// Attributes created by steps after the last attribute selection are included.
+fn output_keys<'a>(
+    steps: &'a [Step],
+    level: impl Fn(&'a Step) -> Option<&'a KeepList>,
+    created: impl Fn(&'a Step) -> Vec<&'a str>,
+) -> Option<Vec<String>> {
+    let kept = KeptKeys::new(steps, &level);
+    let exact = kept.keep_lists.iter().find(|k| k.is_exact())?;
+    let last_selection = steps.iter().rposition(|step| level(step).is_some())?;
+    let mut keys: Vec<String> = Vec::new();
+    let candidates = exact
+        .exact_keys()
+        .filter(|key| kept.keeps(key))
+        .chain(steps[last_selection..].iter().flat_map(created));
+    for key in candidates {
+        if !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+    Some(keys)
+}
+
+/// Global trace (or event) attributes declared for the attributes created by the step, with their default values
+///
+/// Globals must be present in every trace (or event), so attributes only added to some of them
+/// (e.g., the `duration` of collapsed lifecycle pairs) are not declared.
+fn created_globals(step: &Step, trace_level: bool) -> Vec<(&'static str, AttributeValue)> {
+    match step {
+        Step::Enrich(enrichments) => enrichments
+            .iter()
+            .filter(|e| e.is_trace_attribute() == trace_level)
+            .filter_map(|e| Some((e.key(), e.global_default()?)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn set_global(globals: &mut Attributes, key: &str, value: AttributeValue) {
+    globals.remove_with_key(key);
+    globals.add_to_attributes(key.to_string(), value);
+}
+
+/// Rewrite the log header according to the options, after the attribute selection `steps` were applied to the log attributes
+pub fn rewrite_header(log_data: &mut XESOuterLogData, args: &HeaderArgs, steps: &[Step]) {
+    for (key, value) in &args.set_log_attrs {
+        let value = AttributeValue::String(value.clone());
+        match log_data.log_attributes.get_by_key_mut(key) {
+            Some(attribute) => attribute.value = value,
+            None => log_data
+                .log_attributes
+                .add_to_attributes(key.clone(), value),
+        }
+    }
+    let kept_log = KeptKeys::new(steps, |step| match step {
+        Step::KeepAttributes { log, .. } => log.as_ref(),
+        _ => None,
+    });
+    let kept_trace = KeptKeys::new(steps, |step| match step {
+        Step::KeepAttributes { trace, .. } => trace.as_ref(),
+        _ => None,
+    });
+    let kept_event = KeptKeys::new(steps, |step| match step {
+        Step::KeepAttributes { event, .. } => event.as_ref(),
+        _ => None,
+    });
+
+    match args.globals {
+        HeaderMode::Keep => {}
+        HeaderMode::Consistent => {
+            // Apply the steps changing the attributes (in their order) to the globals
+            for step in steps {
+                match step {
+                    Step::KeepAttributes { trace, event, .. } => {
+                        if let Some(trace) = trace {
+                            trace.retain(&mut log_data.global_trace_attrs);
+                        }
+                        if let Some(event) = event {
+                            event.retain(&mut log_data.global_event_attrs);
+                        }
+                    }
+                    Step::Pseudonymize(pseudonymizer) => pseudonymizer.apply_to_globals(
+                        &mut log_data.global_trace_attrs,
+                        &mut log_data.global_event_attrs,
+                    ),
+                    _ => {}
+                }
+                for (key, value) in created_globals(step, true) {
+                    set_global(&mut log_data.global_trace_attrs, key, value);
+                }
+                for (key, value) in created_globals(step, false) {
+                    set_global(&mut log_data.global_event_attrs, key, value);
+                }
+            }
+        }
+        HeaderMode::Drop => {
+            log_data.global_trace_attrs.clear();
+            log_data.global_event_attrs.clear();
+        }
+    }
+    match args.classifiers {
+        HeaderMode::Keep => {}
+        HeaderMode::Consistent => log_data
+            .classifiers
+            .retain(|c| c.keys.iter().all(|k| kept_event.keeps(k))),
+        HeaderMode::Drop => log_data.classifiers.clear(),
+    }
+    match args.extensions {
+        HeaderMode::Keep => {}
+        HeaderMode::Consistent => {
+            // Keys which are known to be used (if present in the input)
+            let used_keys: Vec<&str> = log_data
+                .log_attributes
+                .iter()
+                .chain(&log_data.global_trace_attrs)
+                .chain(&log_data.global_event_attrs)
+                .map(|a| a.key.as_str())
+                .chain(
+                    log_data
+                        .classifiers
+                        .iter()
+                        .flat_map(|c| c.keys.iter().map(|k| k.as_str())),
+                )
+                .chain(kept_trace.exact_keys())
+                .chain(kept_event.exact_keys())
+                .collect();
+            let is_used = |prefix: &str| {
+                used_keys
+                    .iter()
+                    .any(|k| k.split_once(':').is_some_and(|(p, _)| p == prefix))
+            };
+            let mut extensions = std::mem::take(&mut log_data.extensions);
+            extensions.retain(|ext| {
+                is_used(&ext.prefix)
+                    || [&kept_log, &kept_trace, &kept_event]
+                        .iter()
+                        .any(|kept| kept.may_keep_prefix(&ext.prefix))
+            });
+            for (name, prefix, uri) in STANDARD_EXTENSIONS {
+                if is_used(prefix) && !extensions.iter().any(|ext| ext.prefix == prefix) {
+                    extensions.push(EventLogExtension {
+                        name: name.to_string(),
+                        prefix: prefix.to_string(),
+                        uri: uri.to_string(),
+                    });
+                }
+            }
+            log_data.extensions = extensions;
+        }
+        HeaderMode::Drop => log_data.extensions.clear(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use process_mining::event_log::{event_log_struct::EventLogClassifier, Attributes};
+
+    use super::*;
+    use crate::{enrich::Enrichment, pseudonymize::Pseudonymizer};
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        header: HeaderArgs,
+    }
+
+    fn header_args(args: &[&str]) -> HeaderArgs {
+        Cli::parse_from(std::iter::once("test").chain(args.iter().copied())).header
+    }
+
+    fn attributes(attributes: &[(&str, AttributeValue)]) -> Attributes {
+        let mut result = Attributes::new();
+        for (key, value) in attributes {
+            result.add_to_attributes(key.to_string(), value.clone());
+        }
+        result
+    }
+
+    fn string(s: &str) -> AttributeValue {
+        AttributeValue::String(s.to_string())
+    }
+
+    fn extension(name: &str, prefix: &str) -> EventLogExtension {
+        EventLogExtension {
+            name: name.to_string(),
+            prefix: prefix.to_string(),
+            uri: format!("http://example.com/{prefix}.xesext"),
+        }
+    }
+
+    fn log_data() -> XESOuterLogData {
+        XESOuterLogData {
+            extensions: vec![
+                extension("Concept", "concept"),
+                extension("Cost", "cost"),
+                extension("Custom", "custom"),
+            ],
+            classifiers: vec![
+                EventLogClassifier {
+                    name: "Activity".to_string(),
+                    keys: vec!["concept:name".to_string()],
+                },
+                EventLogClassifier {
+                    name: "Resource".to_string(),
+                    keys: vec!["concept:name".to_string(), "org:resource".to_string()],
+                },
+            ],
+            log_attributes: attributes(&[("concept:name", string("Log"))]),
+            global_trace_attrs: attributes(&[
+                ("concept:name", string("__INVALID__")),
+                ("cost:total", AttributeValue::Float(0.0)),
+            ]),
+            global_event_attrs: attributes(&[
+                ("concept:name", string("__INVALID__")),
+                ("org:resource", AttributeValue::Int(0)),
+                ("cost:amount", AttributeValue::Float(0.0)),
+            ]),
+        }
+    }
+
+    fn keep(trace: &[&str], event: &[&str]) -> Step {
+        let keep_list =
+            |keys: &[&str]| Some(KeepList::new(keys.iter().map(|k| k.to_string()).collect()));
+        Step::KeepAttributes {
+            log: keep_list(&["concept:name"]),
+            trace: keep_list(trace),
+            event: keep_list(event),
+        }
+    }
+
+    fn keys(attributes: &Attributes) -> Vec<&str> {
+        attributes.iter().map(|a| a.key.as_str()).collect()
+    }
+
+    #[test]
+    fn keep_and_drop_modes() {
+        let steps = [keep(&["concept:name"], &["concept:name"])];
+        let mut kept = log_data();
+        let args = header_args(&[
+            "--header-extensions=keep",
+            "--header-globals=keep",
+            "--header-classifiers=keep",
+        ]);
+        rewrite_header(&mut kept, &args, &steps);
+        let expected = log_data();
+        assert_eq!(kept.extensions, expected.extensions);
+        assert_eq!(kept.classifiers, expected.classifiers);
+        assert_eq!(kept.global_trace_attrs, expected.global_trace_attrs);
+        assert_eq!(kept.global_event_attrs, expected.global_event_attrs);
+
+        let mut dropped = log_data();
+        let args = header_args(&[
+            "--header-extensions=drop",
+            "--header-globals=drop",
+            "--header-classifiers=drop",
+        ]);
+        rewrite_header(&mut dropped, &args, &steps);
+        assert!(dropped.extensions.is_empty());
+        assert!(dropped.classifiers.is_empty());
+        assert!(dropped.global_trace_attrs.is_empty());
+        assert!(dropped.global_event_attrs.is_empty());
+        assert_eq!(dropped.log_attributes, log_data().log_attributes);
+    }
+
+    #[test]
+    fn consistent_globals_and_classifiers() {
+        let steps = [keep(&["concept:name"], &["concept:name", "org:*"])];
+        let mut data = log_data();
+        rewrite_header(&mut data, &header_args(&[]), &steps);
+        assert_eq!(keys(&data.global_trace_attrs), ["concept:name"]);
+        assert_eq!(
+            keys(&data.global_event_attrs),
+            ["concept:name", "org:resource"]
+        );
+        let classifiers: Vec<&str> = data.classifiers.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(classifiers, ["Activity", "Resource"]);
+
+        let steps = [keep(&["concept:name"], &["concept:name"])];
+        let mut data = log_data();
+        rewrite_header(&mut data, &header_args(&[]), &steps);
+        let classifiers: Vec<&str> = data.classifiers.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(classifiers, ["Activity"]);
+    }
+
+    #[test]
+    fn consistent_globals_of_pseudonymized_attributes() {
+        let pseudonymizer = Pseudonymizer::new(
+            b"secret",
+            vec!["concept:name".to_string()],
+            vec!["org:resource".to_string()],
+        );
+        let steps = [
+            keep(&["*"], &["concept:name", "org:resource"]),
+            Step::Pseudonymize(pseudonymizer),
+        ];
+        let mut data = log_data();
+        rewrite_header(&mut data, &header_args(&[]), &steps);
+        // The defaults are pseudonymized (and have the type of the pseudonyms)
+        let trace_name = &data
+            .global_trace_attrs
+            .get_by_key("concept:name")
+            .unwrap()
+            .value;
+        let resource = &data
+            .global_event_attrs
+            .get_by_key("org:resource")
+            .unwrap()
+            .value;
+        assert!(matches!(trace_name, AttributeValue::String(s) if s != "__INVALID__"));
+        assert!(matches!(resource, AttributeValue::String(_)));
+        assert_eq!(
+            data.global_event_attrs
+                .get_by_key("concept:name")
+                .unwrap()
+                .value,
+            string("__INVALID__")
+        );
+    }
+
+    #[test]
+    fn consistent_globals_of_created_attributes() {
+        let steps = [
+            keep(&["concept:name"], &["concept:name"]),
+            Step::CollapseLifecycle,
+            Step::Enrich(vec![
+                Enrichment::EventIndex,
+                Enrichment::TimeSincePrevious,
+                Enrichment::TimeSinceStart,
+                Enrichment::CaseDuration,
+                Enrichment::NumEvents,
+                Enrichment::VariantHash,
+            ]),
+        ];
+        let mut data = log_data();
+        rewrite_header(&mut data, &header_args(&[]), &steps);
+        // Only attributes added to every trace/event are declared
+        assert_eq!(
+            keys(&data.global_trace_attrs),
+            ["concept:name", "num_events", "variant_hash"]
+        );
+        assert_eq!(
+            keys(&data.global_event_attrs),
+            ["concept:name", "event_index"]
+        );
+
+        // Created attributes removed by a later attribute selection are not declared
+        let steps = [
+            Step::Enrich(vec![Enrichment::EventIndex, Enrichment::NumEvents]),
+            keep(&["concept:name"], &["concept:name"]),
+        ];
+        let mut data = log_data();
+        rewrite_header(&mut data, &header_args(&[]), &steps);
+        assert_eq!(keys(&data.global_trace_attrs), ["concept:name"]);
+        assert_eq!(keys(&data.global_event_attrs), ["concept:name"]);
+    }
+
+    #[test]
+    fn consistent_extensions() {
+        let steps = [keep(&["concept:name"], &["concept:name", "org:resource"])];
+        let mut data = log_data();
+        rewrite_header(&mut data, &header_args(&[]), &steps);
+        // Extensions of removed prefixes are dropped and standard extensions of kept attributes are added
+        let prefixes: Vec<&str> = data.extensions.iter().map(|e| e.prefix.as_str()).collect();
+        assert_eq!(prefixes, ["concept", "org"]);
+        assert_eq!(
+            data.extensions[1].uri,
+            "http://www.xes-standard.org/org.xesext"
+        );
+
+        // Extensions are kept if glob patterns may match attributes with their prefix
+        let steps = [keep(&["*"], &["concept:name", "custom:*"])];
+        let mut data = log_data();
+        rewrite_header(&mut data, &header_args(&[]), &steps);
+        let prefixes: Vec<&str> = data.extensions.iter().map(|e| e.prefix.as_str()).collect();
+        assert_eq!(prefixes, ["concept", "cost", "custom"]);
+    }
+
+    #[test]
+    fn set_log_attributes() {
+        let mut data = log_data();
+        let args = header_args(&[
+            "--set-log-attr",
+            "concept:name=Stripped",
+            "--set-log-attr",
+            "source=x=y",
+        ]);
+        rewrite_header(&mut data, &args, &[]);
+        assert_eq!(
+            data.log_attributes,
+            attributes(&[
+                ("concept:name", string("Stripped")),
+                ("source", string("x=y"))
+            ])
+        );
+    }
+}
@@ -0,0 +1,605 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use flate2::read::MultiGzDecoder;
+use indicatif::ProgressBar;
+use process_mining::{
+    event_log::{
+        import_xes::XESParseError,
+        stream_xes::{XESOuterLogData, XESParsingStreamAndLogData, XESParsingTraceStream},
+        AttributeValue, Attributes, Trace, XESEditableAttribute,
+    },
+    XESImportOptions,
+};
+
+use crate::{
+    error::{xes_error_message, StripError},
+    event_csv::{CsvImportArgs, CsvTraceStream},
+    output::is_csv_path,
+    progress::ProgressCounters,
+    stdio::is_stdio,
+    timestamps::parse_timezone,
+    xes_dates::AssumeTimezoneReader,
+};
+
+/// Options for reading inputs (XES and CSV)
+#[derive(clap::Args, Debug, Clone)]
+#[command(next_help_heading = "Input")]
+pub struct InputArgs {
+    /// Timezone of dates without UTC offset in CSV and XES inputs (IANA name like `Europe/Berlin`, or `UTC`)
+    #[clap(long, value_name = "ZONE", value_parser = parse_timezone, default_value = "UTC")]
+    pub assume_timezone: Tz,
+    #[command(flatten)]
+    pub csv: CsvImportArgs,
+}
+
+/// How to handle traces with a case ID (i.e., trace `concept:name`) which was already used by a previous trace
+///
+/// With `renumber` and `fail`, all case IDs seen so far are held in memory to detect duplicates.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateCaseIds {
+    /// Prefix all case IDs with the name of their source file (e.g., `jan_2023/case_1`)
+    Prefix,
+    /// Append a number to second and later occurrences of a case ID (e.g., `case_1_2`)
+    Renumber,
+    /// Abort with an error
+    Fail,
+    /// Leave duplicate case IDs as they are
+    Keep,
+}
+
+/// How to combine the log-level data (extensions, classifiers, global and log attributes) of multiple inputs
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeHeaders {
+    /// Use the log data of the first input only
+    First,
+    /// Use the union of the log data of all inputs (on conflicting keys, the first input wins)
+    Union,
+}
+
+/// Trace stream of a single input file
+pub enum TraceSource {
+    Xes(XESParsingTraceStream<'static>),
+    Csv(CsvTraceStream),
+}
+
+impl TraceSource {
+    fn next_trace(&mut self) -> Option<Trace> {
+        match self {
+            TraceSource::Xes(stream) => stream.into_iter().next(),
+            TraceSource::Csv(stream) => stream.next(),
+        }
+    }
+
+    fn check_for_errors(&mut self) -> Option<XESParseError> {
+        match self {
+            TraceSource::Xes(stream) => stream.check_for_errors(),
+            TraceSource::Csv(stream) => stream.error.take(),
+        }
+    }
+
+    /// Records skipped while reading the input (invalid rows of CSV inputs)
+    fn skipped(&self) -> SkippedRecords {
+        match self {
+            TraceSource::Xes(_) => SkippedRecords::default(),
+            TraceSource::Csv(stream) => stream.skipped.clone(),
+        }
+    }
+}
+
+/// Maximum number of skipped events and traces logged (per input stream)
+const MAX_LOGGED_SKIPS: u64 = 10;
+
+/// Invalid records skipped instead of aborting (with `--skip-invalid`)
+#[derive(Debug, Clone, Default)]
+pub struct SkippedRecords {
+    /// Events with an attribute value that could not be parsed (or invalid CSV rows)
+    pub events: u64,
+    /// Traces with a trace attribute value that could not be parsed
+    pub traces: u64,
+    /// Inputs whose remaining traces were skipped after a parse error (e.g., malformed XML, after which parsing can not continue)
+    pub truncated_inputs: u64,
+    /// Reasons of the first skipped records (as logged)
+    pub reasons: Vec<String>,
+}
+
+impl SkippedRecords {
+    /// Log the reason for skipping an event or trace (only the first few, to not flood the output)
+    fn log(&mut self, level: &str, reason: String) {
+        let logged = self.events + self.traces;
+        if logged < MAX_LOGGED_SKIPS {
+            eprintln!("Skipping invalid {level}: {reason}");
+            self.reasons.push(reason);
+        } else if logged == MAX_LOGGED_SKIPS {
+            eprintln!("Skipping further invalid events and traces without logging them");
+        }
+    }
+
+    pub fn skip_event(&mut self, reason: impl Display) {
+        self.log("event", reason.to_string());
+        self.events += 1;
+    }
+
+    pub fn skip_trace(&mut self, reason: impl Display) {
+        self.log("trace", reason.to_string());
+        self.traces += 1;
+    }
+
+    pub fn skip_rest_of_input(&mut self, input: &Path, error: impl Display) {
+        eprintln!("Skipping the rest of {input:?} after a parse error: {error}");
+        self.reasons
+            .push(format!("Rest of {input:?} after a parse error: {error}"));
+        self.truncated_inputs += 1;
+    }
+
+    pub fn add(&mut self, other: &SkippedRecords) {
+        self.events += other.events;
+        self.traces += other.traces;
+        self.truncated_inputs += other.truncated_inputs;
+        self.reasons.extend(other.reasons.iter().cloned());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events == 0 && self.traces == 0 && self.truncated_inputs == 0
+    }
+
+    /// Summary of the skipped records (e.g., `Skipped 1 invalid traces and 3 invalid events`)
+    pub fn summary(&self) -> String {
+        let truncated = match self.truncated_inputs {
+            0 => String::new(),
+            n => format!(", and the rest of {n} inputs after a parse error"),
+        };
+        format!(
+            "Skipped {} invalid traces and {} invalid events{truncated}",
+            self.traces, self.events
+        )
+    }
+}
+
+/// Key of the first attribute whose value could not be parsed
+///
+/// (the XES parser only logs such values and imports them as [`AttributeValue::None`])
+fn invalid_attribute(attributes: &Attributes) -> Option<&str> {
+    attributes
+        .iter()
+        .find(|a| matches!(a.value, AttributeValue::None()))
+        .map(|a| a.key.as_str())
+}
+
+/// Case ID of the trace (quoted) for messages
+fn trace_name(trace: &Trace) -> String {
+    match trace
+        .attributes
+        .get_by_key("concept:name")
+        .and_then(|a| a.value.try_as_string())
+    {
+        Some(case_id) => format!("{case_id:?}"),
+        None => "without case ID".to_string(),
+    }
+}
+
+/// Description of the first attribute value of the trace (or of its events) that could not be parsed
+fn invalid_value(trace: &Trace) -> Option<String> {
+    let (level, key) = match invalid_attribute(&trace.attributes) {
+        Some(key) => ("trace", key),
+        None => (
+            "event",
+            trace
+                .events
+                .iter()
+                .find_map(|e| invalid_attribute(&e.attributes))?,
+        ),
+    };
+    Some(format!(
+        "Value of {level} attribute {key:?} could not be parsed (trace {})",
+        trace_name(trace)
+    ))
+}
+
+/// Open an input file as trace stream: CSV if the file ends with `.csv`, otherwise XES
+///
+/// The path `-` reads XES from stdin (gz-compressed if `gzip_stdin` is set).
+/// All bytes read from the file advance the progress bar.
+/// CSV inputs have no log data (i.e., empty extensions, classifiers and attributes).
+/// Dates without UTC offset (of CSV and XES inputs) are interpreted in [`InputArgs::assume_timezone`].
+/// With `skip_invalid`, invalid CSV rows are skipped (see [`CsvTraceStream::skipped`])
+pub fn open_input(
+    path: &Path,
+    options: XESImportOptions,
+    input_args: &InputArgs,
+    gzip_stdin: bool,
+    skip_invalid: bool,
+    progress: &ProgressBar,
+) -> Result<(TraceSource, XESOuterLogData), StripError> {
+    let (file, gzip): (Box<dyn Read>, bool) = if is_stdio(path) {
+        (Box::new(progress.wrap_read(io::stdin())), gzip_stdin)
+    } else {
+        let file = File::open(path).map_err(|error| StripError::InputMissing {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        (
+            Box::new(progress.wrap_read(file)),
+            path.extension().is_some_and(|ext| ext == "gz"),
+        )
+    };
+    if is_csv_path(path) {
+        let stream = CsvTraceStream::open(
+            file,
+            &input_args.csv,
+            input_args.assume_timezone,
+            skip_invalid,
+        )
+        .map_err(|e| StripError::from_xes(path, &e))?;
+        Ok((TraceSource::Csv(stream), XESOuterLogData::default()))
+    } else {
+        let reader: Box<dyn Read> = if gzip {
+            // (split outputs may consist of multiple gzip members)
+            Box::new(MultiGzDecoder::new(file))
+        } else {
+            file
+        };
+        let reader: Box<dyn Read> = if input_args.assume_timezone != Tz::UTC {
+            Box::new(AssumeTimezoneReader::new(
+                BufReader::new(reader),
+                input_args.assume_timezone,
+            ))
+        } else {
+            reader
+        };
+        let reader = Box::new(TruncationCheckReader::new(reader));
+        let (stream, log_data) =
+            stream_xes_reader(reader, options).map_err(|e| StripError::from_xes(path, &e))?;
+        Ok((TraceSource::Xes(stream), log_data))
+    }
+}
+
+/// Open a single input and pass all its traces to `f` (while showing a progress spinner)
+///
+/// Used by subcommands analyzing an input (instead of transforming it). Returns the log data of the input.
+pub fn read_single_input<F: FnMut(Trace)>(
+    path: &Path,
+    input_args: &InputArgs,
+    skip_invalid: bool,
+    mut f: F,
+) -> Result<(XESOuterLogData, SkippedRecords), StripError> {
+    let progress = ProgressBar::new_spinner();
+    let counters = ProgressCounters::default();
+    counters.init_progress_bar(&progress, &[path.to_path_buf()]);
+    progress.set_message(format!("Reading {path:?}..."));
+    let (source, log_data) = open_input(
+        path,
+        XESImportOptions::default(),
+        input_args,
+        false,
+        skip_invalid,
+        &progress,
+    )?;
+    progress.enable_steady_tick(Duration::from_millis(50));
+    let mut trace_stream = MergedTraceStream::new(
+        vec![(path.to_path_buf(), source)],
+        DuplicateCaseIds::Keep,
+        skip_invalid,
+    );
+    trace_stream.by_ref().for_each(|t| {
+        counters.record(&t);
+        f(t)
+    });
+    progress.finish_and_clear();
+    if let Some(err) = trace_stream.error.take() {
+        return Err(err);
+    }
+    Ok((log_data, trace_stream.skipped))
+}
+
+/// Closing tag of a XES document
+const LOG_END_TAG: &[u8] = b"</log>";
+
+/// Reader of a XES document which fails with an IO error if the input ends before the closing `</log>` tag
+///
+/// The XES parser of `process_mining` treats the end of the input as the end of the document,
+/// so a truncated file (e.g., of an interrupted copy) would otherwise be read as a shorter log.
+pub struct TruncationCheckReader<R: Read> {
+    reader: R,
+    /// Last bytes read (as the closing tag can be split between reads)
+    tail: Vec<u8>,
+    closed: bool,
+}
+
+impl<R: Read> TruncationCheckReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            tail: Vec::new(),
+            closed: false,
+        }
+    }
+}
+
+impl<R: Read> Read for TruncationCheckReader<R> {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(dst)?;
+        if !self.closed {
+            if n == 0 && !dst.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Input ends before the closing </log> tag (truncated file?)",
+                ));
+            }
+            self.tail.extend_from_slice(&dst[..n]);
+            self.closed = self
+                .tail
+                .windows(LOG_END_TAG.len())
+                .any(|w| w == LOG_END_TAG);
+            let start = self.tail.len().saturating_sub(LOG_END_TAG.len() - 1);
+            self.tail.drain(..start);
+        }
+        Ok(n)
+    }
+}
+
+/// Stream a XES file from a reader
+pub fn stream_xes_reader(
+    reader: Box<dyn Read>,
+    options: XESImportOptions,
+) -> Result<XESParsingStreamAndLogData<'static>, XESParseError> {
+    XESParsingTraceStream::try_new(
+        Box::new(quick_xml::Reader::from_reader(Box::new(BufReader::new(
+            reader,
+        )))),
+        options,
+    )
+}
+
+/// Name of the source file, without directory and XES/CSV extensions (e.g., `logs/jan_2023.xes.gz` becomes `jan_2023`)
+pub fn source_name(path: &Path) -> String {
+    if is_stdio(path) {
+        return "stdin".to_string();
+    }
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    match file_name.find(".xes").or_else(|| file_name.rfind(".csv")) {
+        Some(i) => file_name[..i].to_string(),
+        None => file_name.to_string(),
+    }
+}
+
+/// Combine the log data of multiple inputs
+pub fn merge_log_data(mut all: Vec<XESOuterLogData>, mode: MergeHeaders) -> XESOuterLogData {
+    if all.is_empty() {
+        return XESOuterLogData::default();
+    }
+    let mut merged = all.remove(0);
+    if mode == MergeHeaders::First {
+        return merged;
+    }
+    fn union_by_key(into: &mut Attributes, from: Attributes) {
+        for a in from {
+            if into.get_by_key(&a.key).is_none() {
+                into.push(a);
+            }
+        }
+    }
+    for log_data in all {
+        for ext in log_data.extensions {
+            if !merged.extensions.contains(&ext) {
+                merged.extensions.push(ext);
+            }
+        }
+        for cl in log_data.classifiers {
+            if !merged.classifiers.iter().any(|c| c.name == cl.name) {
+                merged.classifiers.push(cl);
+            }
+        }
+        union_by_key(&mut merged.log_attributes, log_data.log_attributes);
+        union_by_key(&mut merged.global_trace_attrs, log_data.global_trace_attrs);
+        union_by_key(&mut merged.global_event_attrs, log_data.global_event_attrs);
+    }
+    merged
+}
+
+/// Trace stream over multiple inputs (one after another), handling duplicate case IDs between them
+///
+/// With `skip_invalid`, invalid events and traces are skipped (as well as the rest of an input after a parse error)
+/// instead of ending the stream with an error
+pub struct MergedTraceStream {
+    /// Trace streams and the path of their input
+    sources: Vec<(PathBuf, TraceSource)>,
+    current: usize,
+    duplicates: DuplicateCaseIds,
+    /// Case ID prefix for each source with [`DuplicateCaseIds::Prefix`] (source names, numbered if not unique)
+    prefixes: Vec<String>,
+    /// All case IDs seen so far (only with [`DuplicateCaseIds::Renumber`] and [`DuplicateCaseIds::Fail`])
+    ///
+    /// This grows with the number of traces, as a duplicate can occur anywhere in a later input
+    seen_case_ids: HashSet<String>,
+    skip_invalid: bool,
+    /// Invalid records skipped so far (including those of finished CSV inputs)
+    pub skipped: SkippedRecords,
+    /// Error encountered while parsing or merging (ends the stream)
+    pub error: Option<StripError>,
+}
+
+impl MergedTraceStream {
+    pub fn new(
+        sources: Vec<(PathBuf, TraceSource)>,
+        duplicates: DuplicateCaseIds,
+        skip_invalid: bool,
+    ) -> Self {
+        let mut prefixes: Vec<String> = Vec::new();
+        if duplicates == DuplicateCaseIds::Prefix {
+            for (input, _) in &sources {
+                let name = source_name(input);
+                let prefix = if prefixes.contains(&name) {
+                    (2..)
+                        .map(|n| format!("{name}_{n}"))
+                        .find(|p| !prefixes.contains(p))
+                        .unwrap()
+                } else {
+                    name
+                };
+                prefixes.push(prefix);
+            }
+        }
+        Self {
+            sources,
+            current: 0,
+            duplicates,
+            prefixes,
+            seen_case_ids: HashSet::new(),
+            skip_invalid,
+            skipped: SkippedRecords::default(),
+            error: None,
+        }
+    }
+
+    /// Remove events with an attribute value that could not be parsed
+    ///
+    /// Returns `false` if the whole trace should be skipped (as a trace attribute value could not be parsed)
+    fn skip_invalid_records(&mut self, trace: &mut Trace) -> bool {
+        let case_id = trace_name(trace);
+        if let Some(key) = invalid_attribute(&trace.attributes) {
+            self.skipped.skip_trace(format!(
+                "Value of trace attribute {key:?} could not be parsed (trace {case_id})"
+            ));
+            return false;
+        }
+        let skipped = &mut self.skipped;
+        trace
+            .events
+            .retain(|e| match invalid_attribute(&e.attributes) {
+                Some(key) => {
+                    skipped.skip_event(format!(
+                        "Value of event attribute {key:?} could not be parsed (trace {case_id})"
+                    ));
+                    false
+                }
+                None => true,
+            });
+        true
+    }
+
+    /// Apply the duplicate case ID handling to the trace
+    ///
+    /// Returns an error if a duplicate was found and [`DuplicateCaseIds::Fail`] is used
+    fn handle_case_id(&mut self, trace: &mut Trace) -> Result<(), StripError> {
+        if self.duplicates == DuplicateCaseIds::Keep {
+            return Ok(());
+        }
+        let Some(attr) = trace.attributes.get_by_key_mut("concept:name") else {
+            return Ok(());
+        };
+        let AttributeValue::String(case_id) = &attr.value else {
+            return Ok(());
+        };
+        let mut case_id = case_id.clone();
+        if self.duplicates == DuplicateCaseIds::Prefix {
+            // Source prefixes are unique, so the prefixed case IDs are unique across inputs
+            let prefix = &self.prefixes[self.current];
+            attr.value = AttributeValue::String(format!("{prefix}/{case_id}"));
+            return Ok(());
+        }
+        if self.seen_case_ids.contains(&case_id) {
+            if self.duplicates == DuplicateCaseIds::Fail {
+                return Err(StripError::parse(
+                    &self.sources[self.current].0,
+                    format!("Duplicate case ID {case_id:?}"),
+                ));
+            }
+            // Append a number until the case ID is unique
+            case_id = (2..)
+                .map(|n| format!("{case_id}_{n}"))
+                .find(|id| !self.seen_case_ids.contains(id))
+                .unwrap();
+            attr.value = AttributeValue::String(case_id.clone());
+        }
+        self.seen_case_ids.insert(case_id);
+        Ok(())
+    }
+}
+
+impl Iterator for MergedTraceStream {
+    type Item = Trace;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.error.is_none() && self.current < self.sources.len() {
+            let (input, source) = &mut self.sources[self.current];
+            match source.next_trace() {
+                Some(mut trace) => {
+                    if self.skip_invalid {
+                        if !self.skip_invalid_records(&mut trace) {
+                            continue;
+                        }
+                    } else if let Some(message) = invalid_value(&trace) {
+                        self.error = Some(StripError::parse(input.clone(), message));
+                        return None;
+                    }
+                    if let Err(e) = self.handle_case_id(&mut trace) {
+                        self.error = Some(e);
+                        return None;
+                    }
+                    return Some(trace);
+                }
+                None => {
+                    self.skipped.add(&source.skipped());
+                    if let Some(e) = source.check_for_errors() {
+                        if self.skip_invalid {
+                            self.skipped
+                                .skip_rest_of_input(input, xes_error_message(&e));
+                        } else {
+                            self.error = Some(StripError::from_xes(input.clone(), &e));
+                        }
+                    }
+                    self.current += 1;
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_checked(xml: &[u8], chunk_size: usize) -> io::Result<Vec<u8>> {
+        let mut reader = TruncationCheckReader::new(xml);
+        let mut out = Vec::new();
+        let mut chunk = vec![0; chunk_size];
+        loop {
+            match reader.read(&mut chunk)? {
+                0 => return Ok(out),
+                n => out.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+
+    #[test]
+    fn complete_document_passes_through() {
+        let xml = b"<log><trace><string key=\"concept:name\" value=\"c1\"/></trace></log>\n";
+        for chunk_size in [1, 3, 5, 64] {
+            assert_eq!(read_checked(xml, chunk_size).unwrap(), xml);
+        }
+    }
+
+    #[test]
+    fn truncated_document_fails() {
+        for xml in [
+            &b""[..],
+            b"<log><trace><string key=\"concept:name\" value=\"c1\"/></trace>",
+            b"<log><trace></trace></lo",
+        ] {
+            for chunk_size in [1, 5, 64] {
+                let error = read_checked(xml, chunk_size).unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+            }
+        }
+    }
+}
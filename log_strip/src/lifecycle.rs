@@ -0,0 +1,183 @@
+use process_mining::event_log::{AttributeValue, Event, Trace, XESEditableAttribute};
+
+/// Event attribute keys required for matching start and complete events
+pub const REQUIRED_EVENT_ATTRIBUTES: [&str; 4] = [
+    "concept:name",
+    "concept:instance",
+    "lifecycle:transition",
+    "time:timestamp",
+];
+/// Key of the created attribute containing the duration between start and complete (in seconds)
+pub const DURATION_KEY: &str = "duration";
+/// Key of the created attribute containing the timestamp of the start event
+pub const START_TIMESTAMP_KEY: &str = "start_timestamp";
+
+/// Collapses matching `lifecycle:transition` start/complete pairs into single (complete) events
+///
+/// A complete event is matched with the earliest unmatched start event of the same trace that has the same activity
+/// and instance (`concept:instance`, if present).
+/// The start event is removed and the complete event gets the additional attributes [`DURATION_KEY`] and [`START_TIMESTAMP_KEY`].
+/// Unmatched start or complete events (and events with other transitions) are kept unchanged.
+#[derive(Debug, Default)]
+pub struct LifecycleCollapser {
+    /// Number of collapsed start/complete pairs
+    pub collapsed: u64,
+    pub unmatched_starts: u64,
+    /// Number of unmatched complete events of activities (and instances) which also have start events in their trace
+    ///
+    /// Complete events of activities without any start event are atomic and not counted.
+    pub unmatched_completes: u64,
+}
+
+/// Activity and instance (`concept:instance`) of an event
+type Instance = [Option<AttributeValue>; 2];
+
+impl LifecycleCollapser {
+    pub fn apply(&mut self, trace: &mut Trace) {
+        let mut events: Vec<Option<Event>> = Vec::with_capacity(trace.events.len());
+        // Indices of unmatched start events in `events`
+        let mut open_starts: Vec<usize> = Vec::new();
+        // Instances of all start events, and of the complete events without matching start event
+        let mut started: Vec<Instance> = Vec::new();
+        let mut unmatched_completes: Vec<Instance> = Vec::new();
+        for mut event in trace.events.drain(..) {
+            match transition(&event).as_deref() {
+                Some("start") => {
+                    started.push(instance(&event));
+                    open_starts.push(events.len());
+                    events.push(Some(event));
+                }
+                Some("complete") => {
+                    let matching = open_starts.iter().position(|&i| {
+                        events[i]
+                            .as_ref()
+                            .is_some_and(|start| same_instance(start, &event))
+                    });
+                    match matching {
+                        Some(pos) => {
+                            let start = events[open_starts.remove(pos)].take().unwrap();
+                            collapse(&start, &mut event);
+                            self.collapsed += 1;
+                        }
+                        None => unmatched_completes.push(instance(&event)),
+                    }
+                    events.push(Some(event));
+                }
+                _ => events.push(Some(event)),
+            }
+        }
+        self.unmatched_starts += open_starts.len() as u64;
+        self.unmatched_completes += unmatched_completes
+            .iter()
+            .filter(|i| started.contains(i))
+            .count() as u64;
+        trace.events = events.into_iter().flatten().collect();
+    }
+}
+
+fn transition(event: &Event) -> Option<String> {
+    event
+        .attributes
+        .get_by_key("lifecycle:transition")
+        .and_then(|a| a.value.try_as_string())
+        .map(|t| t.to_lowercase())
+}
+
+fn instance(event: &Event) -> Instance {
+    ["concept:name", "concept:instance"]
+        .map(|key| event.attributes.get_by_key(key).map(|a| a.value.clone()))
+}
+
+fn same_instance(a: &Event, b: &Event) -> bool {
+    ["concept:name", "concept:instance"].iter().all(|key| {
+        a.attributes.get_by_key(key).map(|a| &a.value)
+            == b.attributes.get_by_key(key).map(|a| &a.value)
+    })
+}
+
+/// Add the start timestamp and duration to the complete event
+fn collapse(start: &Event, complete: &mut Event) {
+    let timestamp = |e: &Event| {
+        e.attributes
+            .get_by_key("time:timestamp")
+            .and_then(|a| a.value.try_as_date())
+            .copied()
+    };
+    let Some(start_time) = timestamp(start) else {
+        return;
+    };
+    complete.attributes.remove_with_key(START_TIMESTAMP_KEY);
+    complete.attributes.add_to_attributes(
+        START_TIMESTAMP_KEY.to_string(),
+        AttributeValue::Date(start_time),
+    );
+    if let Some(complete_time) = timestamp(complete) {
+        let duration = (complete_time - start_time).num_milliseconds() as f64 / 1000.0;
+        complete.attributes.remove_with_key(DURATION_KEY);
+        complete
+            .attributes
+            .add_to_attributes(DURATION_KEY.to_string(), AttributeValue::Float(duration));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use process_mining::event_log::Attributes;
+
+    use super::*;
+
+    /// Trace of events given as `activity:transition`
+    fn trace(events: &[&str]) -> Trace {
+        Trace {
+            attributes: Attributes::new(),
+            events: events
+                .iter()
+                .map(|e| {
+                    let (activity, transition) = e.split_once(':').unwrap();
+                    let mut event = Event::new(activity.to_string());
+                    event.attributes.add_to_attributes(
+                        "lifecycle:transition".to_string(),
+                        AttributeValue::String(transition.to_string()),
+                    );
+                    event
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn collapse_pairs() {
+        let mut collapser = LifecycleCollapser::default();
+        let mut t = trace(&[
+            "A:start",
+            "B:start",
+            "A:complete",
+            "B:complete",
+            "C:complete",
+        ]);
+        collapser.apply(&mut t);
+        assert_eq!(t.events.len(), 3);
+        assert_eq!(collapser.collapsed, 2);
+        assert_eq!(collapser.unmatched_starts, 0);
+        assert_eq!(collapser.unmatched_completes, 0);
+    }
+
+    #[test]
+    fn atomic_completes_are_not_unmatched() {
+        let mut collapser = LifecycleCollapser::default();
+        // C has no start event at all, the second A complete has no start event left and B starts too late
+        let mut t = trace(&[
+            "A:start",
+            "A:complete",
+            "A:complete",
+            "C:complete",
+            "B:complete",
+            "B:start",
+        ]);
+        collapser.apply(&mut t);
+        assert_eq!(t.events.len(), 5);
+        assert_eq!(collapser.collapsed, 1);
+        assert_eq!(collapser.unmatched_starts, 1);
+        assert_eq!(collapser.unmatched_completes, 2);
+    }
+}
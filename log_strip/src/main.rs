@@ -1,107 +1,544 @@
-use std::{collections::HashSet, fs::File, path::PathBuf, time::Duration};
+use std::{path::PathBuf, process::ExitCode, time::Instant};
 
-use chrono::prelude::*;
-use clap::Parser;
+use anonymity::KAnonymityMode;
+use batch::BatchArgs;
+use clap::{error::ErrorKind, ArgGroup, CommandFactory, Parser, Subcommand};
+use config::{PipelineConfig, StepConfig, SEED_ENV_VAR};
+use enrich::Enrichment;
+use error::StripError;
+use event_csv::CsvExportArgs;
+use filter::{EventFilter, TraceFilter};
+use header::HeaderArgs;
 use indicatif::ProgressBar;
-use process_mining::{
-    event_log::{import_xes::XESParseError, AttributeValue, XESEditableAttribute},
-    export_xes_trace_stream_to_file, XESImportOptions,
-};
+use input::{DuplicateCaseIds, InputArgs, MergeHeaders};
+use order::OrderArgs;
+use pipeline::Pipeline;
+use pseudonymize::Pseudonymizer;
+use split::SplitArgs;
+use stdio::{status, StdioArgs};
+use timestamps::TimeGranularity;
+
+pub mod activities;
+pub mod anonymity;
+pub mod attributes;
+pub mod batch;
+pub mod config;
+pub mod enrich;
+pub mod error;
+pub mod event_csv;
+pub mod filter;
+pub mod header;
+pub mod input;
+pub mod lifecycle;
+pub mod order;
+pub mod output;
+pub mod pipeline;
+pub mod progress;
+pub mod pseudonymize;
+pub mod sample;
+pub mod split;
+pub mod stats;
+pub mod stdio;
+pub mod timestamps;
+pub mod validate;
+pub mod variants;
+pub mod xes_dates;
 
 #[derive(Parser, Debug)]
 #[command(
     version,
-    about = "A CLI to transform XES event logs using trace streams."
+    about = "A CLI to transform XES (or CSV) event logs using trace streams.",
+    args_conflicts_with_subcommands = true
 )]
+#[command(group(
+    ArgGroup::new("pseudonymization")
+        .args(["pseudonymize", "pipeline"])
+        .multiple(true)
+))]
 struct Args {
-    #[clap(required_unless_present("input"))]
-    input_pos: Option<PathBuf>,
-    #[clap(short, long, required_unless_present("input_pos"))]
-    input: Option<PathBuf>,
-
-    #[clap(required_unless_present("output"))]
-    output_pos: Option<PathBuf>,
-    #[clap(short, long, required_unless_present("output_pos"))]
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Input file(s), followed by the output file (if not specified using `-o`)
+    ///
+    /// Use `-` to read XES from stdin or write XES to stdout
+    #[clap(value_name = "PATHS")]
+    paths: Vec<PathBuf>,
+    /// Input file (can be repeated to merge multiple inputs into one output)
+    #[clap(short, long)]
+    input: Vec<PathBuf>,
+    #[clap(short, long)]
     output: Option<PathBuf>,
+    /// Skip (and count) events and traces with values that can not be parsed instead of aborting
+    ///
+    /// For invalid CSV rows, the row is skipped. Parsing of a XES input can not continue after malformed XML,
+    /// so the rest of that input is skipped
+    #[clap(long)]
+    skip_invalid: bool,
+
+    /// Read the transformation steps from a TOML (or JSON) pipeline file instead of the command line options
+    #[clap(
+        long,
+        value_name = "PATH",
+        help_heading = "Pipeline",
+        conflicts_with_all([
+            "keep_log_attrs", "keep_trace_attrs", "keep_event_attrs", "keep_all",
+            "truncate_time", "round_time", "shift_time", "pseudonymize",
+            "rename_activities", "collapse_lifecycle", "sort_events", "dedup_events",
+            "drop_activities", "keep_activities", "drop_empty_traces",
+            "with_activities", "without_activities", "min_events", "max_events",
+            "window_start", "window_end", "sample_fraction", "sample_count",
+            "k_anonymity", "enrich", "seed", "seed_file", "assume_timezone", "to_timezone",
+        ])
+    )]
+    pipeline: Option<PathBuf>,
+    /// Print the effective pipeline (as TOML pipeline file) without processing any input
+    #[clap(long, help_heading = "Pipeline")]
+    dry_run: bool,
+
+    /// How to handle duplicate case IDs when merging multiple inputs
+    ///
+    /// With `renumber` and `fail`, all case IDs are held in memory to detect duplicates
+    #[clap(long, value_enum, default_value_t = DuplicateCaseIds::Prefix, help_heading = "Merging")]
+    duplicate_case_ids: DuplicateCaseIds,
+    /// How to combine log-level data (extensions, classifiers, global and log attributes) when merging multiple inputs
+    #[clap(long, value_enum, default_value_t = MergeHeaders::Union, help_heading = "Merging")]
+    merge_headers: MergeHeaders,
+
+    /// Log attribute keys to keep (glob patterns like `org:*` are supported)
+    #[clap(long = "keep-log-attr", value_name = "PATTERN")]
+    keep_log_attrs: Vec<String>,
+    /// Trace attribute keys to keep (glob patterns like `org:*` are supported)
+    #[clap(long = "keep-trace-attr", value_name = "PATTERN", default_values = ["concept:name"])]
+    keep_trace_attrs: Vec<String>,
+    /// Event attribute keys to keep (glob patterns like `org:*` are supported)
+    #[clap(long = "keep-event-attr", value_name = "PATTERN", default_values = ["concept:name", "time:timestamp"])]
+    keep_event_attrs: Vec<String>,
+    /// Keep all log, trace and event attributes
+    #[clap(long, conflicts_with_all(["keep_log_attrs", "keep_trace_attrs", "keep_event_attrs"]))]
+    keep_all: bool,
+
+    /// Coarsen all date attributes to the given granularity
+    #[clap(long, value_enum, default_value_t = TimeGranularity::Minute)]
+    truncate_time: TimeGranularity,
+    /// Round date attributes to the closest granularity boundary instead of truncating them
+    #[clap(long)]
+    round_time: bool,
+
+    /// Shift all dates of each trace by a random offset of at most this duration (e.g., `30d` or `12h`)
+    ///
+    /// All dates of a trace are shifted by the same offset, which is derived from the seed and the case ID.
+    /// Durations within a trace are preserved up to the coarsening of dates (`--truncate-time`, applied after shifting)
+    #[clap(long, value_name = "DURATION", value_parser = timestamps::parse_duration)]
+    shift_time: Option<chrono::Duration>,
+    /// Write all dates in this timezone (IANA name like `Europe/Berlin`, or `UTC`), preserving the instant
+    ///
+    /// Dates are also coarsened (e.g., to the local midnight) and split into periods in this timezone
+    #[clap(long, value_name = "ZONE", value_parser = timestamps::parse_timezone, default_value = "UTC")]
+    to_timezone: chrono_tz::Tz,
+    /// Seed for random operations (e.g., `--shift-time` or sampling), for reproducible results
+    ///
+    /// If neither `--seed` nor `--seed-file` is given, the seed is read from the `LOG_STRIP_SEED` environment variable (if set).
+    /// The offsets of `--shift-time` can be recomputed from the seed, so it should be kept as secret as a pseudonymization key
+    #[clap(long)]
+    seed: Option<u64>,
+    /// File containing the seed (instead of `--seed`)
+    #[clap(long, value_name = "PATH", conflicts_with = "seed")]
+    seed_file: Option<PathBuf>,
+
+    /// Replace values of trace/event attributes with keyed-hash (HMAC) pseudonyms
+    ///
+    /// The secret key is read from the file passed with `--pseudonym-key-file` or the `LOG_STRIP_PSEUDONYM_KEY` environment variable
+    #[clap(long)]
+    pseudonymize: bool,
+    /// Trace attribute keys to pseudonymize
+    #[clap(long = "pseudonymize-trace-attr", value_name = "KEY", default_values = ["concept:name"], requires = "pseudonymize")]
+    pseudonymize_trace_attrs: Vec<String>,
+    /// Event attribute keys to pseudonymize
+    #[clap(long = "pseudonymize-event-attr", value_name = "KEY", default_values = ["org:resource"], requires = "pseudonymize")]
+    pseudonymize_event_attrs: Vec<String>,
+    /// File containing the secret key used for pseudonymization
+    #[clap(long, value_name = "PATH", requires = "pseudonymize")]
+    pseudonym_key_file: Option<PathBuf>,
+    /// Write the mapping of original values to pseudonyms to this CSV file (only readable by the owner)
+    #[clap(long, value_name = "PATH", requires = "pseudonymization")]
+    pseudonym_map: Option<PathBuf>,
+
+    /// Rename activities using a CSV mapping file with rows `<activity>,<new label>` (or `re:<pattern>,<new label>`)
+    ///
+    /// Regex patterns need to match the complete activity, and the new label can reference capture groups (e.g., `$1`)
+    #[clap(long, value_name = "PATH", help_heading = "Activity Renaming")]
+    rename_activities: Option<PathBuf>,
+    /// Remove events with an activity renamed to an empty label
+    #[clap(
+        long,
+        requires = "rename_activities",
+        help_heading = "Activity Renaming"
+    )]
+    drop_empty_activities: bool,
+
+    #[command(flatten)]
+    order: OrderArgs,
+
+    /// Collapse `lifecycle:transition` start/complete pairs (of the same activity and instance) into single complete events
+    ///
+    /// The collapsed events get the additional attributes `duration` (in seconds) and `start_timestamp`
+    #[clap(long, help_heading = "Lifecycle")]
+    collapse_lifecycle: bool,
+
+    #[command(flatten)]
+    event_filter: EventFilter,
+
+    #[command(flatten)]
+    filter: TraceFilter,
+
+    /// Randomly sample traces, keeping each (filtered) trace with the given probability
+    #[clap(long, value_name = "FRACTION", value_parser = sample::parse_fraction, help_heading = "Sampling")]
+    sample_fraction: Option<f64>,
+    /// Randomly sample exactly this many (filtered) traces
+    ///
+    /// Sampled traces are kept in memory until the complete input was read
+    #[clap(
+        long,
+        value_name = "N",
+        conflicts_with = "sample_fraction",
+        help_heading = "Sampling"
+    )]
+    sample_count: Option<usize>,
+
+    /// Only keep traces whose variant (activity sequence) occurs at least K times (after filtering and sampling)
+    ///
+    /// The variants are counted in an additional pass over the input(s), so stdin can not be used as input
+    #[clap(long, value_name = "K", help_heading = "k-Anonymity")]
+    k_anonymity: Option<u64>,
+    /// How traces of variants occurring fewer than K times are handled
+    #[clap(long, value_enum, default_value_t = KAnonymityMode::Suppress, requires = "k_anonymity", help_heading = "k-Anonymity")]
+    k_anonymity_mode: KAnonymityMode,
+
+    /// Add derived attributes to each trace or event (computed after coarsening the dates)
+    #[clap(
+        long,
+        value_name = "ATTR",
+        value_enum,
+        value_delimiter = ',',
+        help_heading = "Enrichment"
+    )]
+    enrich: Vec<Enrichment>,
+
+    #[command(flatten)]
+    split: SplitArgs,
+
+    #[command(flatten)]
+    header: HeaderArgs,
+
+    #[command(flatten)]
+    input_args: InputArgs,
+
+    #[command(flatten)]
+    csv: CsvExportArgs,
+
+    #[command(flatten)]
+    batch: BatchArgs,
+
+    #[command(flatten)]
+    stdio: StdioArgs,
 }
 
-fn main() -> Result<(), XESParseError> {
-    let args = Args::parse();
-    let input = args.input.or(args.input_pos).unwrap();
-    let output = args.output.or(args.output_pos).unwrap();
-    println!("Importing from {:?} and exporting to {:?}\n", input, output);
-
-    // XES Import Options: Only process concept:name of traces/events + timestamp of events
-    let options = XESImportOptions {
-        ignore_log_attributes_except: Some(HashSet::default()),
-        ignore_trace_attributes_except: 
-        Some(
-            vec!["concept:name".to_string()].into_iter().collect(),
-        ),
-        ignore_event_attributes_except: Some(
-            vec!["concept:name".to_string(), "time:timestamp".to_string()]
-                .into_iter()
-                .collect(),
-        ),
-        ..XESImportOptions::default()
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print summary statistics of an event log (e.g., number of traces, activities and variants)
+    Stats(stats::StatsArgs),
+    /// Check an event log for data-quality issues (exits with a non-zero code if any issues are found)
+    Validate(validate::ValidateArgs),
+}
+
+impl Args {
+    /// Get input files and output file from the positional and named arguments
+    fn inputs_and_output(&mut self) -> (Vec<PathBuf>, PathBuf) {
+        let mut inputs = std::mem::take(&mut self.input);
+        let output = match self.output.take() {
+            Some(output) => output,
+            None => self.paths.pop().unwrap_or_else(|| {
+                Args::command()
+                    .error(
+                        ErrorKind::MissingRequiredArgument,
+                        "No output file specified",
+                    )
+                    .exit()
+            }),
+        };
+        inputs.append(&mut self.paths);
+        if inputs.is_empty() {
+            Args::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "No input file specified",
+                )
+                .exit()
+        }
+        if inputs.iter().filter(|input| stdio::is_stdio(input)).count() > 1 {
+            Args::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "stdin (`-`) can only be used as one input",
+                )
+                .exit()
+        }
+        if stdio::is_stdio(&output) && self.split.is_active() {
+            Args::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "Split outputs can not be written to stdout (`-`)",
+                )
+                .exit()
+        }
+        (inputs, output)
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = Args::parse();
+    let result = match args.command.take() {
+        Some(Command::Stats(stats_args)) => stats::run(stats_args).map(|_| ExitCode::SUCCESS),
+        Some(Command::Validate(validate_args)) => validate::run(validate_args).map(|valid| {
+            if valid {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }),
+        None => strip(args).map(|_| ExitCode::SUCCESS),
     };
-    // Open file and initialize streaming XES parser
-    let file = File::open(&input).expect("Input file not found");
-    let is_gz = input.extension().is_some_and(|ext| ext == "gz");
-    let stream_res = if is_gz {
-        process_mining::stream_xes_file_gz(file, options)
-    } else {
-        process_mining::stream_xes_file(file, options)
+    result.unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        ExitCode::from(e.exit_code())
+    })
+}
+
+/// Configure the transformation steps from the command line arguments
+///
+/// The steps are applied in a fixed order: sorting/deduplicating events, renaming activities (so that filters apply to the new labels),
+/// collapsing lifecycle pairs, filtering events and traces, sampling, k-anonymity, selecting attributes, shifting and coarsening dates, pseudonymizing
+fn pipeline_config(args: &Args) -> PipelineConfig {
+    let mut steps = Vec::new();
+    if args.order.is_active() {
+        steps.push(StepConfig::OrderEvents(args.order.clone()));
+    }
+    if let Some(mapping) = &args.rename_activities {
+        steps.push(StepConfig::RenameActivities {
+            mapping: mapping.clone(),
+            drop_empty: args.drop_empty_activities,
+        });
+    }
+    if args.collapse_lifecycle {
+        steps.push(StepConfig::CollapseLifecycle);
+    }
+    if args.event_filter.is_active() {
+        steps.push(StepConfig::FilterEvents(args.event_filter.clone()));
+    }
+    if args.filter.is_active() {
+        steps.push(StepConfig::FilterTraces(args.filter.clone()));
+    }
+    if args.sample_fraction.is_some() || args.sample_count.is_some() {
+        steps.push(StepConfig::Sample {
+            fraction: args.sample_fraction,
+            count: args.sample_count,
+        });
+    }
+    if let Some(k) = args.k_anonymity {
+        steps.push(StepConfig::KAnonymity {
+            k,
+            mode: args.k_anonymity_mode,
+        });
+    }
+    if !args.keep_all {
+        let mut keep_event_attrs = args.keep_event_attrs.clone();
+        if args.collapse_lifecycle {
+            // Keep created attributes
+            keep_event_attrs.push(lifecycle::DURATION_KEY.to_string());
+            keep_event_attrs.push(lifecycle::START_TIMESTAMP_KEY.to_string());
+        }
+        steps.push(StepConfig::KeepAttributes {
+            log: Some(args.keep_log_attrs.clone()),
+            trace: Some(args.keep_trace_attrs.clone()),
+            event: Some(keep_event_attrs),
+        });
+    }
+    if let Some(max_shift) = args.shift_time {
+        steps.push(StepConfig::ShiftTime {
+            max_shift: timestamps::format_duration(max_shift),
+        });
+    }
+    if args.truncate_time != TimeGranularity::None {
+        steps.push(StepConfig::CoarsenTime {
+            granularity: args.truncate_time,
+            round: args.round_time,
+        });
+    }
+    if !args.enrich.is_empty() {
+        steps.push(StepConfig::Enrich {
+            attributes: args.enrich.clone(),
+        });
+    }
+    if args.pseudonymize {
+        steps.push(StepConfig::Pseudonymize {
+            trace_attrs: args.pseudonymize_trace_attrs.clone(),
+            event_attrs: args.pseudonymize_event_attrs.clone(),
+            key_file: args.pseudonym_key_file.clone(),
+        });
+    }
+    PipelineConfig {
+        seed: args.seed,
+        seed_file: args.seed_file.clone(),
+        assume_timezone: (args.input_args.assume_timezone != chrono_tz::Tz::UTC)
+            .then(|| args.input_args.assume_timezone.name().to_string()),
+        to_timezone: (args.to_timezone != chrono_tz::Tz::UTC)
+            .then(|| args.to_timezone.name().to_string()),
+        steps,
+    }
+}
+
+/// Configure the transformation pipeline from the pipeline config and the command line arguments
+fn build_pipeline(args: &Args, config: &PipelineConfig) -> Result<Pipeline, StripError> {
+    // Load pseudonymization secrets and activity mappings before starting the (potentially long) export
+    let load_error = |e| StripError::Config(format!("Failed to load pipeline: {e}"));
+    let steps = config
+        .load_steps(args.pseudonym_map.is_some())
+        .map_err(load_error)?;
+    let input_timezone = config.input_timezone().map_err(load_error)?;
+    let timezone = config.timezone().map_err(load_error)?;
+    Ok(Pipeline {
+        // XES Import Options: Only process kept attributes and those required by previous steps
+        options: pipeline::import_options(&steps),
+        steps,
+        input: InputArgs {
+            assume_timezone: input_timezone,
+            csv: args.input_args.csv.clone(),
+        },
+        csv_export: args.csv.clone(),
+        duplicate_case_ids: args.duplicate_case_ids,
+        merge_headers: args.merge_headers,
+        seed: config
+            .load_seed()
+            .map_err(load_error)?
+            .unwrap_or_else(rand::random),
+        split: args.split.clone(),
+        stdio: args.stdio,
+        timezone,
+        skip_invalid: args.skip_invalid,
+        header: args.header.clone(),
+    })
+}
+
+/// Transform the input(s) and export the result
+fn strip(mut args: Args) -> Result<(), StripError> {
+    let config = match &args.pipeline {
+        Some(path) => PipelineConfig::load(path).map_err(|e| {
+            StripError::Config(format!("Failed to read pipeline file {path:?}: {e}"))
+        })?,
+        None => pipeline_config(&args),
     };
-    // Initialize progress spinner
-    let progress = ProgressBar::new_spinner();
-    progress.set_style(
-        indicatif::ProgressStyle::with_template("{spinner} {msg} [Elapsed: {elapsed_precise}]")
-            .unwrap(),
-    );
-    progress.set_message(format!(
-        "Progressing {}...",
-        input.file_name().unwrap_or_default().to_string_lossy()
-    ));
-
-    // Check if streaming parser encountered error
-    match stream_res {
-        Ok((mut trace_stream, log_data)) => {
-            // if not, we can initialize the streaming XES exporter
-            let output_file = File::create(&output)?;
-            // ...and progress the progress spinner
-            progress.enable_steady_tick(Duration::from_millis(50));
-            // Create transformed trace stream, where timestamp seconds and nanoseconds are set to 0
-            let transformed_stream = trace_stream.into_iter().map(|mut t| {
-                t.events.iter_mut().for_each(|e| {
-                    if let Some(t) = e.attributes.get_by_key_mut("time:timestamp") {
-                        if let AttributeValue::Date(d) = t.value {
-                            t.value = AttributeValue::Date(
-                                d.with_second(0).unwrap().with_nanosecond(0).unwrap(),
-                            );
+    if args.pipeline.is_some() && config.exposes_time_shift_seed() {
+        eprintln!(
+            "Warning: The pipeline file contains the seed of `shift_time`, from which the time offsets can be recomputed. \
+            Use `seed_file` or {} instead to keep it out of version control",
+            SEED_ENV_VAR
+        );
+    }
+    if args.dry_run {
+        print!("{}", config.to_toml());
+        return Ok(());
+    }
+    let pipeline = build_pipeline(&args, &config)?;
+    let start = Instant::now();
+    let pseudonymizer = if let Some(output_dir) = args.batch.output_dir.clone() {
+        // Batch mode: Process each input separately
+        let mut paths = std::mem::take(&mut args.input);
+        paths.append(&mut args.paths);
+        if paths.iter().any(|input| stdio::is_stdio(input)) {
+            Args::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "stdin (`-`) can not be used as input in batch mode",
+                )
+                .exit()
+        }
+        let inputs = batch::expand_inputs(&paths)?;
+        let outputs = batch::output_paths(&inputs, &output_dir)?;
+        std::fs::create_dir_all(&output_dir).map_err(|e| StripError::write(&output_dir, e))?;
+        println!(
+            "Processing {} inputs and exporting to {:?}\n",
+            inputs.len(),
+            output_dir
+        );
+        let jobs = args
+            .batch
+            .jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+        let results = batch::run_batch(&pipeline, &inputs, &outputs, jobs);
+        let mut pseudonymizer: Option<Pseudonymizer> = None;
+        let mut failed = 0;
+        let mut exit_code = None;
+        for ((input, output), result) in inputs.iter().zip(&outputs).zip(results) {
+            match result {
+                Ok(summary) => {
+                    println!("Exported {input:?} to {output:?}");
+                    summary.print();
+                    if let Some(p) = summary.pseudonymizer {
+                        match &mut pseudonymizer {
+                            Some(merged) => merged.merge_mapping(p),
+                            None => pseudonymizer = Some(p),
                         }
                     }
-                });
-                // Return transformed trace
-                t
+                }
+                Err(e) => {
+                    eprintln!("Failed to process {input:?}: {e}");
+                    failed += 1;
+                    exit_code.get_or_insert(e.exit_code());
+                }
+            }
+        }
+        if let Some(exit_code) = exit_code {
+            return Err(StripError::Batch {
+                failed,
+                total: inputs.len(),
+                exit_code,
             });
-            // Start streaming XES export with transformed trace stream
-            export_xes_trace_stream_to_file(
-                transformed_stream,
-                log_data,
-                output_file,
-                output.extension().is_some_and(|ext| ext == "gz"),
-            )
-            .inspect_err(|e| eprintln!("XES Export Failed: {e}"))?;
-            // Finish spinner
-            progress.finish();
-            // Done :)
-            println!("\n\nStreaming export finished in {:?}", progress.elapsed());
-            Ok(())
         }
-        Err(err) => {
-            eprintln!("Failed to import XES: {}", err);
-            Err(err)
+        pseudonymizer
+    } else {
+        let (inputs, output) = args.inputs_and_output();
+        if stdio::is_stdio(&output) {
+            // Keep stdout clean for the exported log
+            stdio::print_status_to_stderr();
         }
+        status!(
+            "Importing from {:?} and exporting to {:?}\n",
+            inputs,
+            output
+        );
+        // Initialize progress bar
+        let progress = ProgressBar::new_spinner();
+        progress.set_message(format!(
+            "Progressing {}...",
+            inputs
+                .iter()
+                .map(|input| input.file_name().unwrap_or_default().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+        let summary = pipeline.run(&inputs, &output, &progress)?;
+        // Finish progress bar
+        progress.finish();
+        summary.print();
+        summary.pseudonymizer
+    };
+    if let (Some(pseudonymizer), Some(path)) = (&pseudonymizer, &args.pseudonym_map) {
+        pseudonymizer
+            .write_mapping(path)
+            .map_err(|e| StripError::write(path, e))?;
     }
+    // Done :)
+    status!("\n\nStreaming export finished in {:?}", start.elapsed());
+    Ok(())
 }
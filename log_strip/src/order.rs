@@ -0,0 +1,294 @@
+use std::cmp::Ordering;
+
+use process_mining::event_log::{AttributeValue, Event, Trace, XESEditableAttribute};
+use serde::{Deserialize, Serialize};
+
+/// Options for repairing the order of events within traces
+#[derive(clap::Args, Debug, Clone, Default, Serialize, Deserialize)]
+#[command(next_help_heading = "Event Order")]
+#[serde(default, deny_unknown_fields)]
+pub struct OrderArgs {
+    /// Sort the events of each trace by `time:timestamp` (stable, i.e., events with equal timestamps keep their order)
+    ///
+    /// Events without timestamp are moved to the end of the trace
+    #[clap(long)]
+    pub sort_events: bool,
+    /// Event attribute used to order events with equal timestamps (e.g., `concept:name` or a sequence number)
+    #[clap(long, value_name = "KEY", requires = "sort_events")]
+    pub sort_tie_breaker: Option<String>,
+    /// Remove exact duplicate events (i.e., with all attributes equal) within each trace, keeping the first occurrence
+    #[clap(long)]
+    pub dedup_events: bool,
+}
+
+impl OrderArgs {
+    pub fn is_active(&self) -> bool {
+        self.sort_events || self.dedup_events
+    }
+
+    pub fn required_event_attributes(&self) -> Vec<&str> {
+        let mut keys = Vec::new();
+        if self.sort_events {
+            keys.push("time:timestamp");
+            keys.extend(self.sort_tie_breaker.as_deref());
+        }
+        keys
+    }
+}
+
+/// Sorts and deduplicates events within traces, counting the changes
+#[derive(Debug)]
+pub struct EventOrderer {
+    args: OrderArgs,
+    /// Number of traces whose events were reordered
+    pub reordered_traces: u64,
+    pub removed_duplicates: u64,
+}
+
+impl EventOrderer {
+    pub fn new(args: OrderArgs) -> Self {
+        Self {
+            args,
+            reordered_traces: 0,
+            removed_duplicates: 0,
+        }
+    }
+
+    pub fn apply(&mut self, trace: &mut Trace) {
+        if self.args.sort_events {
+            let tie_breaker = self.args.sort_tie_breaker.as_deref();
+            let compare = |a: &Event, b: &Event| {
+                compare_values(value(a, "time:timestamp"), value(b, "time:timestamp")).then_with(
+                    || match tie_breaker {
+                        Some(key) => compare_values(value(a, key), value(b, key)),
+                        None => Ordering::Equal,
+                    },
+                )
+            };
+            if !trace.events.is_sorted_by(|a, b| compare(a, b).is_le()) {
+                trace.events.sort_by(compare);
+                self.reordered_traces += 1;
+            }
+        }
+        if self.args.dedup_events {
+            let duplicates = duplicate_events(&trace.events);
+            let mut index = 0;
+            trace.events.retain(|_| {
+                index += 1;
+                !duplicates[index - 1]
+            });
+            self.removed_duplicates += duplicates.iter().filter(|&&d| d).count() as u64;
+        }
+    }
+}
+
+/// Which events are exact duplicates of an earlier event
+///
+/// Duplicates have equal timestamps, so only events within runs of equal timestamps (in a stable order by timestamp) are compared.
+fn duplicate_events(events: &[Event]) -> Vec<bool> {
+    let timestamp = |i: usize| value(&events[i], "time:timestamp");
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.sort_by(|&a, &b| compare_values(timestamp(a), timestamp(b)));
+    let mut duplicates = vec![false; events.len()];
+    for run in order.chunk_by(|&a, &b| compare_values(timestamp(a), timestamp(b)).is_eq()) {
+        for (n, &i) in run.iter().enumerate() {
+            duplicates[i] = run[..n]
+                .iter()
+                .any(|&j| !duplicates[j] && events[j] == events[i]);
+        }
+    }
+    duplicates
+}
+
+fn value<'a>(event: &'a Event, key: &str) -> Option<&'a AttributeValue> {
+    event.attributes.get_by_key(key).map(|a| &a.value)
+}
+
+/// Compare two attribute values, with missing values last
+///
+/// Values of different types are ordered by their type (see [`type_rank`]), values of the same non-primitive type are considered equal.
+fn compare_values(a: Option<&AttributeValue>, b: Option<&AttributeValue>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => match (a, b) {
+            (AttributeValue::Date(a), AttributeValue::Date(b)) => a.cmp(b),
+            (AttributeValue::String(a), AttributeValue::String(b)) => a.cmp(b),
+            (AttributeValue::Int(a), AttributeValue::Int(b)) => a.cmp(b),
+            (AttributeValue::Float(a), AttributeValue::Float(b)) => a.total_cmp(b),
+            (AttributeValue::Boolean(a), AttributeValue::Boolean(b)) => a.cmp(b),
+            (AttributeValue::ID(a), AttributeValue::ID(b)) => a.cmp(b),
+            (a, b) => type_rank(a).cmp(&type_rank(b)),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Position of the type of the value in the order of values of different types
+fn type_rank(value: &AttributeValue) -> u8 {
+    match value {
+        AttributeValue::Date(_) => 0,
+        AttributeValue::String(_) => 1,
+        AttributeValue::Int(_) => 2,
+        AttributeValue::Float(_) => 3,
+        AttributeValue::Boolean(_) => 4,
+        AttributeValue::ID(_) => 5,
+        AttributeValue::List(_) => 6,
+        AttributeValue::Container(_) => 7,
+        AttributeValue::None() => 8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use process_mining::event_log::Attributes;
+
+    use super::*;
+
+    fn date(s: &str) -> AttributeValue {
+        AttributeValue::Date(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc))
+    }
+
+    fn event(
+        activity: &str,
+        timestamp: Option<AttributeValue>,
+        extra: &[(&str, AttributeValue)],
+    ) -> Event {
+        let mut event = Event::new(activity.to_string());
+        if let Some(timestamp) = timestamp {
+            event
+                .attributes
+                .add_to_attributes("time:timestamp".to_string(), timestamp);
+        }
+        for (key, value) in extra {
+            event
+                .attributes
+                .add_to_attributes(key.to_string(), value.clone());
+        }
+        event
+    }
+
+    fn trace(events: Vec<Event>) -> Trace {
+        Trace {
+            attributes: Attributes::new(),
+            events,
+        }
+    }
+
+    fn activities(trace: &Trace) -> Vec<String> {
+        trace
+            .events
+            .iter()
+            .map(|e| {
+                value(e, "concept:name")
+                    .unwrap()
+                    .try_as_string()
+                    .unwrap()
+                    .clone()
+            })
+            .collect()
+    }
+
+    fn orderer(
+        sort_events: bool,
+        sort_tie_breaker: Option<&str>,
+        dedup_events: bool,
+    ) -> EventOrderer {
+        EventOrderer::new(OrderArgs {
+            sort_events,
+            sort_tie_breaker: sort_tie_breaker.map(str::to_string),
+            dedup_events,
+        })
+    }
+
+    #[test]
+    fn sort_is_stable() {
+        let mut t = trace(vec![
+            event("C", Some(date("2023-01-01T12:00:00Z")), &[]),
+            event("B", Some(date("2023-01-01T10:00:00Z")), &[]),
+            event("A", Some(date("2023-01-01T12:00:00Z")), &[]),
+            event("X", None, &[]),
+            event("D", Some(date("2023-01-01T11:00:00Z")), &[]),
+        ]);
+        let mut orderer = orderer(true, None, false);
+        orderer.apply(&mut t);
+        // Equal timestamps keep their order, events without timestamp are moved to the end
+        assert_eq!(activities(&t), ["B", "D", "C", "A", "X"]);
+        assert_eq!(orderer.reordered_traces, 1);
+        orderer.apply(&mut t);
+        assert_eq!(orderer.reordered_traces, 1);
+    }
+
+    #[test]
+    fn sort_with_tie_breaker() {
+        let seq = |n| ("seq", AttributeValue::Int(n));
+        let mut t = trace(vec![
+            event("C", Some(date("2023-01-01T12:00:00Z")), &[seq(2)]),
+            event("A", Some(date("2023-01-01T12:00:00Z")), &[seq(1)]),
+            event("B", Some(date("2023-01-01T12:00:00Z")), &[]),
+            event("D", Some(date("2023-01-01T11:00:00Z")), &[seq(9)]),
+        ]);
+        orderer(true, Some("seq"), false).apply(&mut t);
+        // Events without the tie-breaker attribute come last among equal timestamps
+        assert_eq!(activities(&t), ["D", "A", "C", "B"]);
+    }
+
+    #[test]
+    fn sort_mismatched_types_consistently() {
+        let mut t = trace(vec![
+            event("S", Some(AttributeValue::String("x".to_string())), &[]),
+            event("D", Some(date("2023-01-01T12:00:00Z")), &[]),
+            event("I", Some(AttributeValue::Int(0)), &[]),
+        ]);
+        orderer(true, None, false).apply(&mut t);
+        assert_eq!(activities(&t), ["D", "S", "I"]);
+    }
+
+    #[test]
+    fn dedup_exact_duplicates() {
+        let time = || Some(date("2023-01-01T12:00:00Z"));
+        let mut t = trace(vec![
+            event("A", time(), &[("cost", AttributeValue::Int(1))]),
+            event("B", Some(date("2023-01-01T13:00:00Z")), &[]),
+            event("A", time(), &[("cost", AttributeValue::Int(1))]),
+            event("X", None, &[]),
+            event("X", None, &[]),
+            event("A", time(), &[("cost", AttributeValue::Int(1))]),
+        ]);
+        let mut orderer = orderer(false, None, true);
+        orderer.apply(&mut t);
+        // Without sorting, the first occurrences keep their positions
+        assert_eq!(activities(&t), ["A", "B", "X"]);
+        assert_eq!(orderer.removed_duplicates, 3);
+    }
+
+    #[test]
+    fn dedup_keeps_near_duplicates() {
+        let time = || Some(date("2023-01-01T12:00:00Z"));
+        let mut t = trace(vec![
+            event("A", time(), &[("cost", AttributeValue::Int(1))]),
+            // Different value
+            event("A", time(), &[("cost", AttributeValue::Int(2))]),
+            // Different type
+            event("A", time(), &[("cost", AttributeValue::Float(1.0))]),
+            event(
+                "A",
+                time(),
+                &[("cost", AttributeValue::String("1".to_string()))],
+            ),
+            // Missing attribute
+            event("A", time(), &[]),
+            // Different timestamp
+            event(
+                "A",
+                Some(date("2023-01-01T12:00:01Z")),
+                &[("cost", AttributeValue::Int(1))],
+            ),
+        ]);
+        let mut orderer = orderer(false, None, true);
+        orderer.apply(&mut t);
+        assert_eq!(t.events.len(), 6);
+        assert_eq!(orderer.removed_duplicates, 0);
+    }
+}
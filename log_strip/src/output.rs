@@ -0,0 +1,205 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use chrono_tz::Tz;
+use flate2::{write::GzEncoder, Compression};
+use process_mining::event_log::{
+    export_xes::export_xes as export_xes_document, stream_xes::XESOuterLogData, Attributes, Trace,
+};
+
+use crate::{
+    event_csv::{export_csv_trace_stream, CsvColumns},
+    stdio::is_stdio,
+    xes_dates,
+};
+
+/// Check if the path should be imported/exported as event CSV (instead of XES)
+pub fn is_csv_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "csv")
+}
+
+/// Options for exporting a trace stream (independent of the output path)
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub csv: CsvColumns,
+    /// Compress XES written to stdout using gzip
+    pub gzip_stdout: bool,
+    /// Timezone in which dates are written
+    pub timezone: Tz,
+}
+
+/// Export a trace stream to a file, with the format determined by the file extension
+///
+/// * `-`: XES written to stdout (gz-compressed if [`ExportOptions::gzip_stdout`] is set)
+/// * `.csv`: Flat event CSV (see [`export_csv_trace_stream`])
+/// * `.gz`: gz-compressed XES
+/// * otherwise: XES
+pub fn export_to_path<I>(
+    traces: I,
+    log_data: XESOuterLogData,
+    path: &Path,
+    options: &ExportOptions,
+) -> io::Result<()>
+where
+    I: Iterator<Item = Trace>,
+{
+    if is_stdio(path) {
+        return export_xes(
+            traces,
+            log_data,
+            io::stdout().lock(),
+            options.gzip_stdout,
+            options.timezone,
+        );
+    }
+    let file = File::create(path)?;
+    if is_csv_path(path) {
+        export_csv_trace_stream(
+            traces,
+            &log_data,
+            BufWriter::new(file),
+            &options.csv,
+            options.timezone,
+        )?
+        .flush()
+    } else {
+        export_xes(
+            traces,
+            log_data,
+            file,
+            path.extension().is_some_and(|ext| ext == "gz"),
+            options.timezone,
+        )
+    }
+}
+
+/// Export a trace stream as (optionally gz-compressed) XES
+fn export_xes<I, W>(
+    traces: I,
+    log_data: XESOuterLogData,
+    writer: W,
+    gzip: bool,
+    tz: Tz,
+) -> io::Result<()>
+where
+    I: Iterator<Item = Trace>,
+    W: Write,
+{
+    let writer = BufWriter::new(writer);
+    if gzip {
+        let encoder = BufWriter::new(GzEncoder::new(writer, Compression::fast()));
+        let encoder = write_traces(XesWriter::new(encoder, &log_data, tz)?, traces)?;
+        let encoder = encoder.into_inner().map_err(|e| e.into_error())?;
+        encoder.finish()?.flush()?;
+    } else {
+        write_traces(XesWriter::new(writer, &log_data, tz)?, traces)?.flush()?;
+    }
+    Ok(())
+}
+
+fn write_traces<I, W>(mut xes: XesWriter<W>, traces: I) -> io::Result<W>
+where
+    I: Iterator<Item = Trace>,
+    W: Write,
+{
+    for trace in traces {
+        xes.write_trace(&trace)?;
+    }
+    xes.finish()
+}
+
+/// Closing tag of the `log` element (i.e., the end of each exported XES document)
+const LOG_END: &[u8] = b"</log>";
+
+/// Streaming XES writer using the exporter of `process_mining`
+///
+/// The log header and each trace are exported separately, so that traces can be written one at a time (e.g., when splitting).
+/// Dates are written in the given timezone: As the exporter always writes dates in UTC, they are rewritten in the exported XML
+/// (see [`xes_dates::to_timezone`]).
+pub struct XesWriter<W: Write> {
+    writer: W,
+    tz: Tz,
+    /// Exported XES document (of the log header or a single trace)
+    buf: Vec<u8>,
+}
+
+impl<W: Write> XesWriter<W> {
+    /// Write the XML declaration, the opening `log` element and the log data (empty globals are not written)
+    pub fn new(writer: W, log_data: &XESOuterLogData, tz: Tz) -> io::Result<Self> {
+        let mut xes = Self::resume(writer, tz);
+        let globals = |attributes| Some(attributes).filter(|a: &&Attributes| !a.is_empty());
+        xes.export(|buf| {
+            export_xes_document(
+                buf,
+                &Some(&log_data.extensions),
+                &globals(&log_data.global_trace_attrs),
+                &globals(&log_data.global_event_attrs),
+                &Some(&log_data.classifiers),
+                &log_data.log_attributes,
+                std::iter::empty::<Trace>(),
+            )
+        })?;
+        let header = xes.buf.strip_suffix(LOG_END).expect(NO_LOG_END);
+        xes.writer.write_all(header)?;
+        Ok(xes)
+    }
+
+    /// Continue writing traces to a writer appending to the document of a previous writer (see [`XesWriter::suspend`])
+    pub fn resume(writer: W, tz: Tz) -> Self {
+        Self {
+            writer,
+            tz,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn write_trace(&mut self, trace: &Trace) -> io::Result<()> {
+        self.export(|buf| {
+            export_xes_document(
+                buf,
+                &None,
+                &None,
+                &None,
+                &None,
+                &Attributes::new(),
+                std::iter::once(trace),
+            )
+        })?;
+        let document = self.buf.strip_suffix(LOG_END).expect(NO_LOG_END);
+        let start = document
+            .windows(TRACE_START.len())
+            .position(|w| w == TRACE_START)
+            .expect("Exported XES contains the trace");
+        self.writer.write_all(&document[start..])
+    }
+
+    /// Write the closing `log` element, returning the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(LOG_END)?;
+        Ok(self.writer)
+    }
+
+    /// Stop writing without closing the `log` element, returning the inner writer (e.g., to close the file and resume later)
+    pub fn suspend(self) -> W {
+        self.writer
+    }
+
+    /// Export a complete XES document to the buffer (with dates in the timezone of the writer)
+    fn export<F>(&mut self, export: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut Vec<u8>) -> Result<(), quick_xml::Error>,
+    {
+        self.buf.clear();
+        export(&mut self.buf).map_err(io::Error::other)?;
+        if self.tz != Tz::UTC {
+            self.buf = xes_dates::to_timezone(&self.buf, self.tz)?;
+        }
+        Ok(())
+    }
+}
+
+const NO_LOG_END: &str = "Exported XES ends with </log>";
+const TRACE_START: &[u8] = b"<trace";
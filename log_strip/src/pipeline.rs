@@ -0,0 +1,555 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use indicatif::ProgressBar;
+use process_mining::{
+    event_log::{stream_xes::XESOuterLogData, Attributes, Trace},
+    XESImportOptions,
+};
+
+use crate::{
+    activities::ActivityMapping,
+    anonymity::{KAnonymityMode, KAnonymizer},
+    attributes::KeepList,
+    enrich::{self, Enrichment},
+    error::StripError,
+    event_csv::CsvExportArgs,
+    filter::{EventFilter, TraceFilter},
+    header::{self, HeaderArgs},
+    input::{self, DuplicateCaseIds, InputArgs, MergeHeaders, MergedTraceStream, SkippedRecords},
+    lifecycle::{self, LifecycleCollapser},
+    order::{EventOrderer, OrderArgs},
+    output::{self, ExportOptions},
+    progress::ProgressCounters,
+    pseudonymize::Pseudonymizer,
+    sample::{self, Sampling},
+    split::{self, SplitArgs},
+    stdio::{self, status, StdioArgs},
+    timestamps::{for_each_date_mut, TimeGranularity, TimeShifter},
+};
+
+/// All configured transformations, from reading the input(s) to exporting the output
+///
+/// The pipeline itself is not modified when running it, so that it can be run multiple times (e.g., in parallel for batch processing).
+pub struct Pipeline {
+    /// Transformation steps applied to each trace, in this order
+    pub steps: Vec<Step>,
+    pub options: XESImportOptions,
+    pub input: InputArgs,
+    pub csv_export: CsvExportArgs,
+    pub duplicate_case_ids: DuplicateCaseIds,
+    pub merge_headers: MergeHeaders,
+    pub seed: u64,
+    pub split: SplitArgs,
+    pub stdio: StdioArgs,
+    /// Timezone in which dates are coarsened and written
+    pub timezone: Tz,
+    /// Skip invalid events and traces (and the rest of an input after a parse error) instead of aborting
+    pub skip_invalid: bool,
+    pub header: HeaderArgs,
+}
+
+/// A single (loaded) transformation step (see [`crate::config::StepConfig`] for its configuration)
+pub enum Step {
+    OrderEvents(OrderArgs),
+    RenameActivities(ActivityMapping),
+    CollapseLifecycle,
+    FilterEvents(EventFilter),
+    FilterTraces(TraceFilter),
+    /// Sample from the traces left after the previous steps
+    Sample(Sampling),
+    /// Attributes to keep on log/trace/event level (None: keep all)
+    KeepAttributes {
+        log: Option<KeepList>,
+        trace: Option<KeepList>,
+        event: Option<KeepList>,
+    },
+    ShiftTime(chrono::Duration),
+    /// Coarsen dates by truncating (or rounding) them to the granularity (in the timezone of the output)
+    CoarsenTime {
+        granularity: TimeGranularity,
+        round: bool,
+    },
+    /// Add derived attributes (see [`Enrichment`]), computed from the trace as it reaches this step
+    Enrich(Vec<Enrichment>),
+    /// Pseudonymizer (cloned for each run, so that each run records its own mapping)
+    Pseudonymize(Pseudonymizer),
+    /// Remove (or generalize) traces whose variant occurs fewer than `k` times among the traces reaching this step
+    ///
+    /// The variants are counted in an additional pass over the input(s)
+    KAnonymity {
+        k: u64,
+        mode: KAnonymityMode,
+    },
+}
+
+impl Step {
+    /// Event attributes which must be available (i.e., not ignored during parsing) for the step
+    pub fn required_event_attributes(&self) -> Vec<&str> {
+        match self {
+            Step::OrderEvents(order) => order.required_event_attributes(),
+            Step::RenameActivities(_) => vec!["concept:name"],
+            Step::CollapseLifecycle => lifecycle::REQUIRED_EVENT_ATTRIBUTES.to_vec(),
+            Step::FilterEvents(filter) if filter.is_active() => vec!["concept:name"],
+            Step::FilterTraces(filter) => filter.required_event_attributes(),
+            Step::KAnonymity { .. } => vec!["concept:name"],
+            Step::Enrich(enrichments) => enrichments
+                .iter()
+                .flat_map(|e| e.required_event_attributes())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Trace attributes added by the step
+    pub fn created_trace_attributes(&self) -> Vec<&str> {
+        match self {
+            Step::Enrich(enrichments) => enrichments
+                .iter()
+                .filter(|e| e.is_trace_attribute())
+                .map(|e| e.key())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Event attributes added by the step
+    pub fn created_event_attributes(&self) -> Vec<&str> {
+        match self {
+            Step::CollapseLifecycle => {
+                vec![lifecycle::START_TIMESTAMP_KEY, lifecycle::DURATION_KEY]
+            }
+            Step::Enrich(enrichments) => enrichments
+                .iter()
+                .filter(|e| !e.is_trace_attribute())
+                .map(|e| e.key())
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// XES import options only parsing the attributes kept by the first [`Step::KeepAttributes`]
+/// and those required by the steps before it (if they can be determined without glob matching)
+pub fn import_options(steps: &[Step]) -> XESImportOptions {
+    let mut required_event_attributes = Vec::new();
+    for step in steps {
+        if let Step::KeepAttributes { log, trace, event } = step {
+            return XESImportOptions {
+                ignore_log_attributes_except: log.as_ref().and_then(|k| k.as_import_allowlist(&[])),
+                // (case IDs are needed for merging)
+                ignore_trace_attributes_except: trace
+                    .as_ref()
+                    .and_then(|k| k.as_import_allowlist(&["concept:name"])),
+                ignore_event_attributes_except: event
+                    .as_ref()
+                    .and_then(|k| k.as_import_allowlist(&required_event_attributes)),
+                ..XESImportOptions::default()
+            };
+        }
+        required_event_attributes.extend(step.required_event_attributes());
+    }
+    XESImportOptions::default()
+}
+
+/// State of a step during a single run
+enum StepState<'a> {
+    Stateless(&'a Step),
+    OrderEvents(EventOrderer),
+    CollapseLifecycle(LifecycleCollapser),
+    ShiftTime(TimeShifter),
+    CoarsenTime {
+        granularity: TimeGranularity,
+        round: bool,
+        timezone: Tz,
+    },
+    Pseudonymize(Pseudonymizer),
+    KAnonymity(KAnonymizer),
+}
+
+impl<'a> StepState<'a> {
+    fn new(step: &'a Step, seed: u64, timezone: Tz) -> Self {
+        match step {
+            Step::OrderEvents(order) => StepState::OrderEvents(EventOrderer::new(order.clone())),
+            Step::CollapseLifecycle => StepState::CollapseLifecycle(LifecycleCollapser::default()),
+            Step::ShiftTime(max_shift) => StepState::ShiftTime(TimeShifter::new(*max_shift, seed)),
+            Step::CoarsenTime { granularity, round } => StepState::CoarsenTime {
+                granularity: *granularity,
+                round: *round,
+                timezone,
+            },
+            Step::Pseudonymize(pseudonymizer) => StepState::Pseudonymize(pseudonymizer.clone()),
+            Step::KAnonymity { k, mode } => StepState::KAnonymity(KAnonymizer::new(*k, *mode)),
+            step => StepState::Stateless(step),
+        }
+    }
+
+    fn is_sample(&self) -> bool {
+        matches!(self, StepState::Stateless(Step::Sample(_)))
+    }
+
+    /// Apply the step to the log attributes (only attribute selection and coarsening affect them)
+    fn apply_to_log(&self, log_attributes: &mut Attributes) {
+        match self {
+            StepState::Stateless(Step::KeepAttributes { log: Some(log), .. }) => {
+                log.retain(log_attributes)
+            }
+            StepState::CoarsenTime {
+                granularity,
+                round,
+                timezone,
+            } => for_each_date_mut(log_attributes, &mut |d| {
+                *d = granularity.coarsen(*d, *round, *timezone)
+            }),
+            _ => {}
+        }
+    }
+
+    /// Apply the step to the trace
+    ///
+    /// Returns `false` if the trace should be dropped
+    fn apply(&mut self, trace: &mut Trace) -> bool {
+        match self {
+            StepState::OrderEvents(event_orderer) => event_orderer.apply(trace),
+            StepState::CollapseLifecycle(lifecycle_collapser) => lifecycle_collapser.apply(trace),
+            StepState::ShiftTime(time_shifter) => return time_shifter.apply(trace),
+            StepState::CoarsenTime {
+                granularity,
+                round,
+                timezone,
+            } => {
+                let mut coarsen_date =
+                    |d: &mut DateTime<Utc>| *d = granularity.coarsen(*d, *round, *timezone);
+                for_each_date_mut(&mut trace.attributes, &mut coarsen_date);
+                for e in trace.events.iter_mut() {
+                    for_each_date_mut(&mut e.attributes, &mut coarsen_date);
+                }
+            }
+            StepState::Pseudonymize(pseudonymizer) => pseudonymizer.apply(trace),
+            StepState::KAnonymity(k_anonymizer) => return k_anonymizer.apply(trace),
+            StepState::Stateless(step) => match step {
+                Step::RenameActivities(activity_mapping) => activity_mapping.apply(trace),
+                Step::FilterEvents(event_filter) => return event_filter.apply(trace),
+                Step::FilterTraces(filter) => return filter.matches(trace),
+                Step::Enrich(enrichments) => enrich::enrich(trace, enrichments),
+                Step::KeepAttributes {
+                    trace: keep_trace,
+                    event: keep_event,
+                    ..
+                } => {
+                    if let Some(keep_trace) = keep_trace {
+                        keep_trace.retain(&mut trace.attributes);
+                    }
+                    if let Some(keep_event) = keep_event {
+                        for e in trace.events.iter_mut() {
+                            keep_event.retain(&mut e.attributes);
+                        }
+                    }
+                }
+                // Sampling is applied to the whole stream, and all other steps are stateful
+                _ => {}
+            },
+        }
+        true
+    }
+}
+
+/// Apply all steps to the trace, stopping at the first step dropping it
+fn apply_steps(steps: &mut [StepState], trace: &mut Trace) -> bool {
+    steps.iter_mut().all(|step| step.apply(trace))
+}
+
+/// Results of a single run of the pipeline
+pub struct RunSummary {
+    /// Paths of all written output files
+    pub written: Vec<PathBuf>,
+    pub event_orderer: Option<EventOrderer>,
+    pub lifecycle_collapser: Option<LifecycleCollapser>,
+    pub pseudonymizer: Option<Pseudonymizer>,
+    /// Results of each k-anonymity step (in the order of the steps)
+    pub k_anonymizers: Vec<KAnonymizer>,
+    pub skipped: SkippedRecords,
+}
+
+impl RunSummary {
+    /// Collect the results of the stateful steps (combining multiple steps of the same kind, except for k-anonymity steps with different k)
+    fn new(written: Vec<PathBuf>, steps: Vec<StepState>, skipped: SkippedRecords) -> Self {
+        let mut summary = RunSummary {
+            written,
+            skipped,
+            event_orderer: None,
+            lifecycle_collapser: None,
+            pseudonymizer: None,
+            k_anonymizers: Vec::new(),
+        };
+        for step in steps {
+            match step {
+                StepState::OrderEvents(o) => match &mut summary.event_orderer {
+                    Some(merged) => {
+                        merged.reordered_traces += o.reordered_traces;
+                        merged.removed_duplicates += o.removed_duplicates;
+                    }
+                    None => summary.event_orderer = Some(o),
+                },
+                StepState::CollapseLifecycle(c) => match &mut summary.lifecycle_collapser {
+                    Some(merged) => {
+                        merged.collapsed += c.collapsed;
+                        merged.unmatched_starts += c.unmatched_starts;
+                        merged.unmatched_completes += c.unmatched_completes;
+                    }
+                    None => summary.lifecycle_collapser = Some(c),
+                },
+                StepState::Pseudonymize(p) => match &mut summary.pseudonymizer {
+                    Some(merged) => merged.merge_mapping(p),
+                    None => summary.pseudonymizer = Some(p),
+                },
+                StepState::KAnonymity(a) => summary.k_anonymizers.push(a),
+                _ => {}
+            }
+        }
+        summary
+    }
+
+    pub fn print(&self) {
+        if self.written.len() > 1 {
+            status!("Exported {} files:", self.written.len());
+            for path in &self.written {
+                status!("  {path:?}");
+            }
+        }
+        if let Some(o) = &self.event_orderer {
+            status!(
+                "Reordered events of {} traces and removed {} duplicate events",
+                o.reordered_traces,
+                o.removed_duplicates
+            );
+        }
+        if let Some(c) = &self.lifecycle_collapser {
+            status!(
+                "Collapsed {} start/complete pairs (kept {} unmatched start and {} unmatched complete events)",
+                c.collapsed, c.unmatched_starts, c.unmatched_completes
+            );
+        }
+        if !self.skipped.is_empty() {
+            status!("{}", self.skipped.summary());
+        }
+        for a in &self.k_anonymizers {
+            status!(
+                "Found {} variants occurring fewer than {} times: removed {} traces and truncated {} traces to a prefix",
+                a.rare_variants,
+                a.k(),
+                a.suppressed_traces,
+                a.generalized_traces
+            );
+        }
+    }
+}
+
+impl Pipeline {
+    /// Open the input(s) and merge them into a single trace stream
+    fn open_inputs(
+        &self,
+        inputs: &[PathBuf],
+        progress: &ProgressBar,
+    ) -> Result<(MergedTraceStream, XESOuterLogData), StripError> {
+        // Open files and initialize streaming XES (or CSV) parsers
+        let streams = inputs
+            .iter()
+            .map(|input| {
+                input::open_input(
+                    input,
+                    self.options.clone(),
+                    &self.input,
+                    self.stdio.gzip_in,
+                    self.skip_invalid,
+                    progress,
+                )
+                .map(|(stream, log_data)| ((input.clone(), stream), log_data))
+            })
+            .collect::<Result<Vec<_>, StripError>>()?;
+        // Merge the streams into one
+        let (sources, all_log_data): (Vec<_>, Vec<_>) = streams.into_iter().unzip();
+        let log_data = input::merge_log_data(all_log_data, self.merge_headers);
+        // (duplicate case IDs are only handled when merging multiple inputs)
+        let duplicates = if sources.len() > 1 {
+            self.duplicate_case_ids
+        } else {
+            DuplicateCaseIds::Keep
+        };
+        Ok((
+            MergedTraceStream::new(sources, duplicates, self.skip_invalid),
+            log_data,
+        ))
+    }
+
+    /// Initialize the stateful steps for a pass, using the given (finished) k-anonymizers for the first k-anonymity steps
+    fn init_steps(&self, k_anonymizers: Vec<KAnonymizer>) -> Vec<StepState<'_>> {
+        let mut k_anonymizers = k_anonymizers.into_iter();
+        self.steps
+            .iter()
+            .map(|step| match step {
+                Step::KAnonymity { .. } => match k_anonymizers.next() {
+                    Some(k_anonymizer) => StepState::KAnonymity(k_anonymizer),
+                    None => StepState::new(step, self.seed, self.timezone),
+                },
+                _ => StepState::new(step, self.seed, self.timezone),
+            })
+            .collect()
+    }
+
+    /// Apply the steps to each trace, where sampling steps split the steps into segments
+    /// (as sampling needs the whole stream of traces left after the previous segment)
+    fn transform<'s>(
+        &self,
+        traces: impl Iterator<Item = Trace> + 's,
+        steps: &'s mut [StepState],
+        counters: &'s ProgressCounters,
+    ) -> Box<dyn Iterator<Item = Trace> + 's> {
+        let samplings: Vec<Sampling> = self
+            .steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::Sample(sampling) => Some(*sampling),
+                _ => None,
+            })
+            .collect();
+        let mut segments = steps.split_mut(StepState::is_sample);
+        let first_segment = segments
+            .next()
+            .expect("Splitting yields at least one segment");
+        let mut transformed_stream: Box<dyn Iterator<Item = Trace>> =
+            Box::new(traces.filter_map(|mut t| {
+                counters.record(&t);
+                apply_steps(first_segment, &mut t).then_some(t)
+            }));
+        for (sampling, segment) in samplings.into_iter().zip(segments) {
+            let sampled_stream = sample::sample_traces(transformed_stream, sampling, self.seed);
+            transformed_stream = Box::new(
+                sampled_stream.filter_map(|mut t| apply_steps(segment, &mut t).then_some(t)),
+            );
+        }
+        transformed_stream
+    }
+
+    /// Count the variants for all k-anonymity steps, with one pass over the input(s) per step
+    ///
+    /// Each pass applies the previous (finished) k-anonymity steps, so that each step counts exactly the traces reaching it
+    fn count_variants(
+        &self,
+        inputs: &[PathBuf],
+        progress: &ProgressBar,
+    ) -> Result<Vec<KAnonymizer>, StripError> {
+        let passes = self
+            .steps
+            .iter()
+            .filter(|step| matches!(step, Step::KAnonymity { .. }))
+            .count();
+        if passes > 0 && inputs.iter().any(|input| stdio::is_stdio(input)) {
+            return Err(StripError::Config(
+                "k-anonymity needs to read the input(s) multiple times, so stdin (`-`) can not be used as input".to_string(),
+            ));
+        }
+        let message = progress.message();
+        let mut k_anonymizers = Vec::new();
+        for pass in 0..passes {
+            progress.set_message(format!(
+                "{message} (counting variants, pass {}/{})",
+                pass + 1,
+                passes + 1
+            ));
+            let counters = ProgressCounters::default();
+            counters.init_progress_bar(progress, inputs);
+            let (mut trace_stream, _) = self.open_inputs(inputs, progress)?;
+            progress.enable_steady_tick(Duration::from_millis(50));
+            let mut steps = self.init_steps(k_anonymizers);
+            // (the counting step drops all traces, so nothing is left after it)
+            self.transform(trace_stream.by_ref(), &mut steps, &counters)
+                .for_each(drop);
+            if let Some(err) = trace_stream.error.take() {
+                return Err(err);
+            }
+            k_anonymizers = steps
+                .into_iter()
+                .filter_map(|step| match step {
+                    StepState::KAnonymity(k_anonymizer) => Some(k_anonymizer),
+                    _ => None,
+                })
+                .take(pass + 1)
+                .map(|mut k_anonymizer| {
+                    k_anonymizer.finish();
+                    k_anonymizer
+                })
+                .collect();
+            progress.reset();
+        }
+        if passes > 0 {
+            progress.set_message(format!(
+                "{message} (exporting, pass {}/{})",
+                passes + 1,
+                passes + 1
+            ));
+        }
+        Ok(k_anonymizers)
+    }
+
+    /// Stream the input(s) through all transformations and export the result to `output`
+    ///
+    /// The progress bar is ticked while streaming
+    pub fn run(
+        &self,
+        inputs: &[PathBuf],
+        output: &Path,
+        progress: &ProgressBar,
+    ) -> Result<RunSummary, StripError> {
+        let k_anonymizers = self.count_variants(inputs, progress)?;
+        let counters = ProgressCounters::default();
+        counters.init_progress_bar(progress, inputs);
+        let (mut trace_stream, mut log_data) = self.open_inputs(inputs, progress)?;
+        progress.enable_steady_tick(Duration::from_millis(50));
+
+        // Stateful steps are initialized for each run (except for the k-anonymizers of the counting passes)
+        let mut steps = self.init_steps(k_anonymizers);
+        for step in &steps {
+            step.apply_to_log(&mut log_data.log_attributes);
+        }
+        header::rewrite_header(&mut log_data, &self.header, &self.steps);
+        let transformed_stream = self.transform(trace_stream.by_ref(), &mut steps, &counters);
+        let export_options = ExportOptions {
+            csv: self.csv_export.columns(&self.steps),
+            gzip_stdout: self.stdio.gzip_out,
+            timezone: self.timezone,
+        };
+        let written = if self.split.is_active() {
+            // Start streaming export to multiple files
+            split::export_split(
+                transformed_stream,
+                log_data,
+                output,
+                &self.split,
+                &export_options,
+            )?
+        } else {
+            // Start streaming export (XES or CSV) with transformed trace stream
+            output::export_to_path(transformed_stream, log_data, output, &export_options)
+                .map_err(|e| StripError::write(output, e))?;
+            vec![output.to_path_buf()]
+        };
+        // Check if streaming parser (or merging) encountered an error after the log data was parsed
+        if let Some(err) = trace_stream.error.take() {
+            return Err(err);
+        }
+        for step in &mut steps {
+            if let StepState::ShiftTime(TimeShifter {
+                error: Some(err), ..
+            }) = step
+            {
+                return Err(StripError::Config(std::mem::take(err)));
+            }
+        }
+        Ok(RunSummary::new(written, steps, trace_stream.skipped))
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use indicatif::{ProgressBar, ProgressState, ProgressStyle};
+use process_mining::event_log::Trace;
+
+/// Live counters of processed traces and events, shown in the progress bar
+///
+/// The progress bar position itself is the number of bytes read from the input files (see [`ProgressBar::wrap_read`]),
+/// i.e., compressed bytes for gz-compressed inputs.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressCounters {
+    traces: Arc<AtomicU64>,
+    events: Arc<AtomicU64>,
+}
+
+impl ProgressCounters {
+    pub fn record(&self, trace: &Trace) {
+        self.traces.fetch_add(1, Ordering::Relaxed);
+        self.events
+            .fetch_add(trace.events.len() as u64, Ordering::Relaxed);
+    }
+
+    /// Set up the progress bar for reading the inputs, showing the counters (with the total size of the inputs as length, if known)
+    pub fn init_progress_bar(&self, progress: &ProgressBar, inputs: &[PathBuf]) {
+        let total_size = inputs
+            .iter()
+            .map(|input| std::fs::metadata(input).ok().map(|m| m.len()))
+            .sum::<Option<u64>>();
+        let template = match total_size {
+            Some(_) => "{spinner} {msg} [{elapsed_precise}] [{bar:30}] {bytes}/{total_bytes} ({bytes_per_sec}, ETA {eta}) | {traces} traces, {events} events ({traces_per_sec} traces/s)",
+            None => "{spinner} {msg} [{elapsed_precise}] {bytes} ({bytes_per_sec}) | {traces} traces, {events} events ({traces_per_sec} traces/s)",
+        };
+        let (traces, events, traces_per_sec) = (
+            self.traces.clone(),
+            self.events.clone(),
+            self.traces.clone(),
+        );
+        progress.set_style(
+            ProgressStyle::with_template(template)
+                .unwrap()
+                .progress_chars("=> ")
+                .with_key(
+                    "traces",
+                    move |_: &ProgressState, w: &mut dyn std::fmt::Write| {
+                        write!(w, "{}", traces.load(Ordering::Relaxed)).unwrap()
+                    },
+                )
+                .with_key(
+                    "events",
+                    move |_: &ProgressState, w: &mut dyn std::fmt::Write| {
+                        write!(w, "{}", events.load(Ordering::Relaxed)).unwrap()
+                    },
+                )
+                .with_key(
+                    "traces_per_sec",
+                    move |state: &ProgressState, w: &mut dyn std::fmt::Write| {
+                        let secs = state.elapsed().as_secs_f64();
+                        let traces = traces_per_sec.load(Ordering::Relaxed) as f64;
+                        write!(w, "{:.0}", if secs > 0.0 { traces / secs } else { 0.0 }).unwrap()
+                    },
+                ),
+        );
+        if let Some(total_size) = total_size {
+            progress.set_length(total_size);
+        }
+    }
+}
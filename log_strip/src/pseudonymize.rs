@@ -0,0 +1,168 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io,
+    path::Path,
+};
+
+use hmac::{Hmac, Mac};
+use process_mining::event_log::{AttributeValue, Attributes, Trace};
+use sha2::Sha256;
+
+/// Environment variable from which the pseudonymization secret is read if no key file is specified
+pub const SECRET_ENV_VAR: &str = "LOG_STRIP_PSEUDONYM_KEY";
+
+/// Number of hex characters of the HMAC used as pseudonym
+const PSEUDONYM_LENGTH: usize = 20;
+
+/// Load the pseudonymization secret from the given file or (if no file is given) from [`SECRET_ENV_VAR`]
+///
+/// Trailing whitespace/newlines are ignored
+pub fn load_secret(key_file: Option<&Path>) -> io::Result<Vec<u8>> {
+    let secret = match key_file {
+        Some(path) => std::fs::read_to_string(path)?,
+        None => std::env::var(SECRET_ENV_VAR).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No pseudonymization key file specified and {SECRET_ENV_VAR} is not set"),
+            )
+        })?,
+    };
+    let secret = secret.trim_end();
+    if secret.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Pseudonymization secret is empty",
+        ));
+    }
+    Ok(secret.as_bytes().to_vec())
+}
+
+/// Replaces attribute values of traces and events with keyed-hash (HMAC-SHA256) pseudonyms
+///
+/// The same secret always yields the same pseudonym for a value, so that exports of the same source can be joined
+#[derive(Clone)]
+pub struct Pseudonymizer {
+    mac: Hmac<Sha256>,
+    trace_keys: Vec<String>,
+    event_keys: Vec<String>,
+    /// Recorded mapping (attribute key -> original value -> pseudonym), if enabled
+    mapping: Option<BTreeMap<String, BTreeMap<String, String>>>,
+}
+
+impl Pseudonymizer {
+    pub fn new(secret: &[u8], trace_keys: Vec<String>, event_keys: Vec<String>) -> Self {
+        Self {
+            mac: Hmac::new_from_slice(secret).expect("HMAC accepts keys of any length"),
+            trace_keys,
+            event_keys,
+            mapping: None,
+        }
+    }
+
+    /// Record all replaced values, so that they can be written using [`Pseudonymizer::write_mapping`] later
+    pub fn record_mapping(mut self) -> Self {
+        self.mapping = Some(BTreeMap::new());
+        self
+    }
+
+    /// Pseudonymize the configured trace and event attributes of the trace
+    pub fn apply(&mut self, trace: &mut Trace) {
+        let Self {
+            mac,
+            trace_keys,
+            event_keys,
+            mapping,
+        } = self;
+        pseudonymize_attributes(mac, mapping, &mut trace.attributes, trace_keys);
+        for e in trace.events.iter_mut() {
+            pseudonymize_attributes(mac, mapping, &mut e.attributes, event_keys);
+        }
+    }
+
+    /// Pseudonymize the default values of the global trace and event attributes (without recording them), so that they have the type of the pseudonyms
+    pub fn apply_to_globals(&self, trace_globals: &mut Attributes, event_globals: &mut Attributes) {
+        pseudonymize_attributes(&self.mac, &mut None, trace_globals, &self.trace_keys);
+        pseudonymize_attributes(&self.mac, &mut None, event_globals, &self.event_keys);
+    }
+
+    /// Add the recorded mapping of another pseudonymizer (using the same secret) to the mapping of this one
+    pub fn merge_mapping(&mut self, other: Pseudonymizer) {
+        if let (Some(mapping), Some(other)) = (&mut self.mapping, other.mapping) {
+            for (key, values) in other {
+                mapping.entry(key).or_default().extend(values);
+            }
+        }
+    }
+
+    /// Write the recorded mapping as CSV (columns: attribute, original, pseudonym)
+    ///
+    /// On Unix, the file is only readable by its owner
+    pub fn write_mapping(&self, path: &Path) -> io::Result<()> {
+        let Some(mapping) = &self.mapping else {
+            return Ok(());
+        };
+        let mut writer = csv::Writer::from_writer(create_protected_file(path)?);
+        writer.write_record(["attribute", "original", "pseudonym"])?;
+        for (key, values) in mapping {
+            for (original, pseudonym) in values {
+                writer.write_record([key, original, pseudonym])?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+fn hmac_pseudonym(mac: &Hmac<Sha256>, value: &str) -> String {
+    let mut mac = mac.clone();
+    mac.update(value.as_bytes());
+    let hash = mac.finalize().into_bytes();
+    let mut s: String = hash.iter().map(|b| format!("{b:02x}")).collect();
+    s.truncate(PSEUDONYM_LENGTH);
+    s
+}
+
+fn pseudonymize_attributes(
+    mac: &Hmac<Sha256>,
+    mapping: &mut Option<BTreeMap<String, BTreeMap<String, String>>>,
+    attributes: &mut Attributes,
+    keys: &[String],
+) {
+    for a in attributes.iter_mut().filter(|a| keys.contains(&a.key)) {
+        // Only pseudonymize values which can identify something (i.e., no dates, floats, ...)
+        let original = match &a.value {
+            AttributeValue::String(s) => s.clone(),
+            AttributeValue::Int(i) => i.to_string(),
+            AttributeValue::ID(id) => id.to_string(),
+            _ => continue,
+        };
+        let pseudonym = hmac_pseudonym(mac, &original);
+        if let Some(mapping) = mapping {
+            mapping
+                .entry(a.key.clone())
+                .or_default()
+                .insert(original, pseudonym.clone());
+        }
+        a.value = AttributeValue::String(pseudonym);
+    }
+}
+
+/// Create (or truncate) a file only readable by the owner
+///
+/// The mode of [`OpenOptions`] only applies to newly created files, so the permissions of an existing file are reset as well
+fn create_protected_file(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(file)
+}
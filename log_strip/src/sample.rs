@@ -0,0 +1,123 @@
+use process_mining::event_log::Trace;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Separate stream of the random number generator, so that sampling does not correlate with other random operations using the same seed
+const SAMPLING_RNG_STREAM: u64 = 1;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Sampling {
+    /// Keep each trace independently with the given probability (Bernoulli sampling)
+    Fraction(f64),
+    /// Keep exactly the given number of traces (or all, if there are fewer), chosen uniformly at random (reservoir sampling)
+    Count(usize),
+}
+
+/// Sample traces from the trace stream
+///
+/// The relative order of the sampled traces is preserved.
+/// Note that for [`Sampling::Count`], the complete input stream is consumed before the first trace is emitted
+/// and the sampled traces are held in memory.
+pub fn sample_traces<'a, I>(
+    traces: I,
+    sampling: Sampling,
+    seed: u64,
+) -> Box<dyn Iterator<Item = Trace> + 'a>
+where
+    I: Iterator<Item = Trace> + 'a,
+{
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(SAMPLING_RNG_STREAM);
+    match sampling {
+        Sampling::Fraction(fraction) => Box::new(traces.filter(move |_| rng.gen_bool(fraction))),
+        Sampling::Count(count) => {
+            // Algorithm R: The i-th trace replaces a random reservoir entry with probability count/i
+            let mut reservoir: Vec<(usize, Trace)> = Vec::with_capacity(count);
+            for (i, t) in traces.enumerate() {
+                if reservoir.len() < count {
+                    reservoir.push((i, t));
+                } else {
+                    let j = rng.gen_range(0..=i);
+                    if j < count {
+                        reservoir[j] = (i, t);
+                    }
+                }
+            }
+            reservoir.sort_by_key(|(i, _)| *i);
+            Box::new(reservoir.into_iter().map(|(_, t)| t))
+        }
+    }
+}
+
+/// Parse a sampling fraction (between 0 and 1)
+pub fn parse_fraction(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(f) if (0.0..=1.0).contains(&f) => Ok(f),
+        _ => Err(format!(
+            "Invalid fraction {s:?} (expected a number between 0 and 1)"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use process_mining::event_log::{AttributeValue, Attributes, XESEditableAttribute};
+
+    use super::*;
+
+    fn traces(n: usize) -> impl Iterator<Item = Trace> {
+        (0..n).map(|i| {
+            let mut attributes = Attributes::new();
+            attributes.add_to_attributes(
+                "concept:name".to_string(),
+                AttributeValue::String(i.to_string()),
+            );
+            Trace {
+                attributes,
+                events: Vec::new(),
+            }
+        })
+    }
+
+    fn case_ids(traces: impl Iterator<Item = Trace>) -> Vec<usize> {
+        traces
+            .map(|t| {
+                let case_id = t.attributes.get_by_key("concept:name").unwrap();
+                case_id.value.try_as_string().unwrap().parse().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sample_count_preserves_order() {
+        let sampled = case_ids(sample_traces(traces(100), Sampling::Count(10), 42));
+        assert_eq!(sampled.len(), 10);
+        assert!(sampled.is_sorted_by(|a, b| a < b));
+        // Reproducible with the same seed
+        assert_eq!(
+            sampled,
+            case_ids(sample_traces(traces(100), Sampling::Count(10), 42))
+        );
+    }
+
+    #[test]
+    fn sample_count_larger_than_input() {
+        let sampled = case_ids(sample_traces(traces(5), Sampling::Count(10), 1));
+        assert_eq!(sampled, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn sample_fraction() {
+        assert_eq!(
+            sample_traces(traces(50), Sampling::Fraction(0.0), 1).count(),
+            0
+        );
+        assert_eq!(
+            sample_traces(traces(50), Sampling::Fraction(1.0), 1).count(),
+            50
+        );
+        let sampled = case_ids(sample_traces(traces(1000), Sampling::Fraction(0.5), 7));
+        assert!((400..600).contains(&sampled.len()));
+        assert!(sampled.is_sorted_by(|a, b| a < b));
+    }
+}
@@ -0,0 +1,427 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{sync_channel, Receiver},
+};
+
+use chrono::Datelike;
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use flate2::{write::GzEncoder, Compression};
+use process_mining::event_log::{
+    stream_xes::XESOuterLogData, AttributeValue, Trace, XESEditableAttribute,
+};
+
+use crate::{
+    error::StripError,
+    event_csv::{CsvColumns, CsvTraceWriter},
+    filter::time_span,
+    output::{is_csv_path, ExportOptions, XesWriter},
+};
+
+/// Number of traces buffered for the export thread before the splitting blocks
+const CHANNEL_BOUND: usize = 128;
+/// Maximum number of output files which are open at the same time
+///
+/// When writing to further partitions, the least recently written file is closed (and reopened in append mode when needed)
+const MAX_OPEN_FILES: usize = 64;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitPeriod {
+    Month,
+    Quarter,
+    Year,
+}
+
+/// Options for splitting the output into multiple files
+#[derive(clap::Args, Debug, Clone)]
+#[group(multiple = false)]
+#[command(next_help_heading = "Splitting")]
+pub struct SplitArgs {
+    /// Write traces to separate output files based on the value of this trace attribute
+    ///
+    /// The attribute needs to be kept (see `--keep-trace-attr`)
+    #[clap(long, value_name = "KEY")]
+    pub split_by_attr: Option<String>,
+    /// Write traces to separate output files based on the calendar period of their first event
+    #[clap(long, value_enum, value_name = "PERIOD")]
+    pub split_by_period: Option<SplitPeriod>,
+    /// Write traces to separate output files containing at most this many traces each
+    #[clap(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub split_chunks: Option<u64>,
+}
+
+impl SplitArgs {
+    pub fn is_active(&self) -> bool {
+        self.split_by_attr.is_some()
+            || self.split_by_period.is_some()
+            || self.split_chunks.is_some()
+    }
+
+    /// Name of the partition the trace belongs to (`index` is the position of the trace in the stream)
+    ///
+    /// Dates and periods are determined in the given timezone.
+    /// Returns `None` if the attribute or timestamps needed for splitting are missing.
+    fn partition(&self, trace: &Trace, index: u64, tz: Tz) -> Option<String> {
+        if let Some(key) = &self.split_by_attr {
+            match trace.attributes.get_by_key(key).map(|a| &a.value) {
+                Some(AttributeValue::String(s)) => Some(s.clone()),
+                Some(AttributeValue::Int(i)) => Some(i.to_string()),
+                Some(AttributeValue::Boolean(b)) => Some(b.to_string()),
+                Some(AttributeValue::ID(id)) => Some(id.to_string()),
+                Some(AttributeValue::Date(d)) => {
+                    Some(d.with_timezone(&tz).date_naive().to_string())
+                }
+                Some(AttributeValue::Float(f)) => Some(f.to_string()),
+                _ => None,
+            }
+        } else if let Some(period) = self.split_by_period {
+            let (first, _) = time_span(trace)?;
+            let first = first.with_timezone(&tz);
+            Some(match period {
+                SplitPeriod::Month => format!("{}-{:02}", first.year(), first.month()),
+                SplitPeriod::Quarter => format!("{}-Q{}", first.year(), first.month0() / 3 + 1),
+                SplitPeriod::Year => first.year().to_string(),
+            })
+        } else {
+            self.split_chunks
+                .map(|chunk_size| (index / chunk_size).to_string())
+        }
+    }
+}
+
+/// Path of the output file for a partition (e.g., `out.xes.gz` becomes `out_2023-01.xes.gz`)
+pub fn partition_path(output: &Path, partition: &str) -> PathBuf {
+    let partition: String = partition
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let file_name = output
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let split_at = file_name
+        .find(".xes")
+        .or_else(|| file_name.rfind('.'))
+        .unwrap_or(file_name.len());
+    let (stem, ext) = file_name.split_at(split_at);
+    output.with_file_name(format!("{stem}_{partition}{ext}"))
+}
+
+/// Assigns a distinct output path to each partition
+///
+/// Partitions whose (sanitized) names map to the same file name, ignoring case, get a numbered suffix (e.g., `out_none_2.xes`).
+struct PartitionPaths<'a> {
+    output: &'a Path,
+    /// Lowercase file names of all assigned paths
+    used: HashSet<String>,
+}
+
+impl PartitionPaths<'_> {
+    fn assign(&mut self, partition: Option<&str>) -> PathBuf {
+        // Traces without the value needed for splitting
+        let name = partition.unwrap_or("none");
+        let mut path = partition_path(self.output, name);
+        let mut suffix = 1;
+        while !self.used.insert(path.to_string_lossy().to_lowercase()) {
+            suffix += 1;
+            path = partition_path(self.output, &format!("{name}_{suffix}"));
+        }
+        if suffix > 1 {
+            let partition = match partition {
+                Some(partition) => format!("{partition:?}"),
+                None => "of traces without value".to_string(),
+            };
+            eprintln!("Warning: Writing partition {partition} to {path:?}, as its file name is already used by another partition");
+        }
+        path
+    }
+}
+
+/// Format of the output files (determined by the output path, see [`export_to_path`])
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Xes,
+    XesGz,
+    Csv,
+}
+
+/// Writer of an open output file
+enum FileWriter {
+    Xes(XesWriter<BufWriter<File>>),
+    XesGz(XesWriter<BufWriter<GzEncoder<BufWriter<File>>>>),
+    Csv(Box<CsvTraceWriter<BufWriter<File>>>),
+}
+
+/// Output file of a partition, which is closed while other partitions are written (and reopened in append mode)
+struct PartitionFile {
+    path: PathBuf,
+    writer: Option<FileWriter>,
+    created: bool,
+    /// Whether the document was finished (i.e., the file must not be reopened)
+    finished: bool,
+    /// Columns of a CSV output (with the attributes which are not reported anymore)
+    csv_columns: CsvColumns,
+    /// Time of the last write (for closing the least recently used file)
+    last_used: u64,
+}
+
+impl PartitionFile {
+    fn open(&mut self, format: OutputFormat, log_data: &XESOuterLogData, tz: Tz) -> io::Result<()> {
+        let file = if self.created {
+            OpenOptions::new().append(true).open(&self.path)?
+        } else {
+            File::create(&self.path)?
+        };
+        let file = BufWriter::new(file);
+        self.writer =
+            Some(match format {
+                OutputFormat::Xes if self.created => FileWriter::Xes(XesWriter::resume(file, tz)),
+                OutputFormat::Xes => FileWriter::Xes(XesWriter::new(file, log_data, tz)?),
+                OutputFormat::XesGz => {
+                    // Each reopening appends a new gzip member
+                    let encoder = BufWriter::new(GzEncoder::new(file, Compression::fast()));
+                    FileWriter::XesGz(if self.created {
+                        XesWriter::resume(encoder, tz)
+                    } else {
+                        XesWriter::new(encoder, log_data, tz)?
+                    })
+                }
+                OutputFormat::Csv if self.created => FileWriter::Csv(Box::new(
+                    CsvTraceWriter::resume(file, &self.csv_columns, tz),
+                )),
+                OutputFormat::Csv => {
+                    FileWriter::Csv(Box::new(CsvTraceWriter::new(file, &self.csv_columns, tz)?))
+                }
+            });
+        self.created = true;
+        Ok(())
+    }
+
+    fn write(&mut self, trace: &Trace) -> io::Result<()> {
+        match self.writer.as_mut().expect("Partition file is open") {
+            FileWriter::Xes(writer) => writer.write_trace(trace),
+            FileWriter::XesGz(writer) => writer.write_trace(trace),
+            FileWriter::Csv(writer) => Ok(writer.write_trace(trace)?),
+        }
+    }
+
+    /// Close the file, finishing the document if `finish` is set (otherwise, the file can be reopened)
+    fn close(&mut self, finish: bool) -> io::Result<()> {
+        self.finished |= finish;
+        match self.writer.take() {
+            None => {}
+            Some(FileWriter::Xes(writer)) => {
+                let file = if finish {
+                    writer.finish()?
+                } else {
+                    writer.suspend()
+                };
+                file.into_inner().map_err(|e| e.into_error())?;
+            }
+            Some(FileWriter::XesGz(writer)) => {
+                let encoder = if finish {
+                    writer.finish()?
+                } else {
+                    writer.suspend()
+                };
+                let encoder = encoder.into_inner().map_err(|e| e.into_error())?;
+                encoder.finish()?.flush()?;
+            }
+            Some(FileWriter::Csv(writer)) => {
+                let (file, columns) = writer.suspend()?;
+                file.into_inner().map_err(|e| e.into_error())?;
+                self.csv_columns = columns;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Message {
+    /// Create the output file of a new partition (with the next index)
+    NewPartition(PathBuf),
+    /// Write the trace to the partition with the index
+    Trace(usize, Trace),
+}
+
+/// Export thread writing the traces to the files of their partitions, keeping at most [`MAX_OPEN_FILES`] files open
+fn write_partitions(
+    messages: Receiver<Message>,
+    log_data: XESOuterLogData,
+    format: OutputFormat,
+    options: ExportOptions,
+    chunks: bool,
+) -> Result<(), StripError> {
+    let mut partitions: Vec<PartitionFile> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    let close = |partition: &mut PartitionFile, finish: bool| {
+        partition
+            .close(finish)
+            .map_err(|e| StripError::write(&partition.path, e))
+    };
+    for (time, message) in (0..).zip(messages) {
+        match message {
+            Message::NewPartition(path) => {
+                // Chunks are written one after another, so previous chunks can be finished right away
+                if chunks {
+                    for i in open.drain(..) {
+                        close(&mut partitions[i], true)?;
+                    }
+                }
+                partitions.push(PartitionFile {
+                    path,
+                    writer: None,
+                    created: false,
+                    finished: false,
+                    csv_columns: options.csv.clone(),
+                    last_used: time,
+                });
+            }
+            Message::Trace(i, trace) => {
+                if partitions[i].writer.is_none() {
+                    if open.len() >= MAX_OPEN_FILES {
+                        let (pos, &lru) = open
+                            .iter()
+                            .enumerate()
+                            .min_by_key(|(_, &j)| partitions[j].last_used)
+                            .unwrap();
+                        open.swap_remove(pos);
+                        close(&mut partitions[lru], false)?;
+                    }
+                    let partition = &mut partitions[i];
+                    partition
+                        .open(format, &log_data, options.timezone)
+                        .map_err(|e| StripError::write(&partition.path, e))?;
+                    open.push(i);
+                }
+                let partition = &mut partitions[i];
+                partition.last_used = time;
+                partition
+                    .write(&trace)
+                    .map_err(|e| StripError::write(&partition.path, e))?;
+            }
+        }
+    }
+    for i in open {
+        close(&mut partitions[i], true)?;
+    }
+    // Finish the documents of closed files (CSV files do not need to be finished)
+    for partition in partitions.iter_mut().filter(|p| !p.finished) {
+        if !matches!(format, OutputFormat::Csv) {
+            partition
+                .open(format, &log_data, options.timezone)
+                .map_err(|e| StripError::write(&partition.path, e))?;
+        }
+        close(partition, true)?;
+    }
+    Ok(())
+}
+
+/// Export the trace stream to multiple files (one per partition), each with the same log data (and CSV columns)
+///
+/// The files are written by a single export thread, which keeps at most [`MAX_OPEN_FILES`] files open at the same time.
+/// Returns the paths of all written files
+pub fn export_split<I>(
+    traces: I,
+    log_data: XESOuterLogData,
+    output: &Path,
+    split: &SplitArgs,
+    options: &ExportOptions,
+) -> Result<Vec<PathBuf>, StripError>
+where
+    I: Iterator<Item = Trace>,
+{
+    let mut traces = traces.peekable();
+    let mut options = options.clone();
+    options.csv.infer(&log_data, traces.peek());
+    let format = if is_csv_path(output) {
+        OutputFormat::Csv
+    } else if output.extension().is_some_and(|ext| ext == "gz") {
+        OutputFormat::XesGz
+    } else {
+        OutputFormat::Xes
+    };
+    let tz = options.timezone;
+    let (sender, receiver) = sync_channel::<Message>(CHANNEL_BOUND);
+    let chunks = split.split_chunks.is_some();
+    let handle =
+        std::thread::spawn(move || write_partitions(receiver, log_data, format, options, chunks));
+
+    let mut paths = PartitionPaths {
+        output,
+        used: HashSet::new(),
+    };
+    let mut indices: HashMap<Option<String>, usize> = HashMap::new();
+    let mut written: Vec<PathBuf> = Vec::new();
+    for (index, trace) in traces.enumerate() {
+        let partition = split.partition(&trace, index as u64, tz);
+        let i = match indices.get(&partition) {
+            Some(&i) => i,
+            None => {
+                let path = paths.assign(partition.as_deref());
+                written.push(path.clone());
+                indices.insert(partition, written.len() - 1);
+                if sender.send(Message::NewPartition(path)).is_err() {
+                    break;
+                }
+                written.len() - 1
+            }
+        };
+        // The receiver only hangs up if the export failed, so joining returns the export error
+        if sender.send(Message::Trace(i, trace)).is_err() {
+            break;
+        }
+    }
+    drop(sender);
+    handle
+        .join()
+        .expect("Export thread for split output panicked")?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partition_path_keeps_extensions() {
+        assert_eq!(
+            partition_path(Path::new("out/log.xes.gz"), "2023-01"),
+            PathBuf::from("out/log_2023-01.xes.gz")
+        );
+        assert_eq!(
+            partition_path(Path::new("log.xes"), "dept_1"),
+            PathBuf::from("log_dept_1.xes")
+        );
+        assert_eq!(
+            partition_path(Path::new("log.v2.csv"), "a"),
+            PathBuf::from("log.v2_a.csv")
+        );
+        assert_eq!(
+            partition_path(Path::new("log"), "a"),
+            PathBuf::from("log_a")
+        );
+    }
+
+    #[test]
+    fn partition_path_sanitizes_names() {
+        assert_eq!(
+            partition_path(Path::new("log.xes"), "../a b/c"),
+            PathBuf::from("log____a_b_c.xes")
+        );
+        assert_eq!(
+            partition_path(Path::new("log.xes"), "Köln"),
+            PathBuf::from("log_Köln.xes")
+        );
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use process_mining::event_log::{stream_xes::XESOuterLogData, AttributeValue, Attributes, Trace};
+use serde::Serialize;
+
+use crate::{
+    error::StripError,
+    filter::{activity, time_span},
+    input::{self, InputArgs},
+    variants::{Variant, VariantInterner},
+};
+
+/// Maximum number of activities listed in the table output
+const TABLE_ACTIVITY_LIMIT: usize = 20;
+
+/// Output format of reports (e.g., of the `stats` subcommand)
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Human-readable table
+    Table,
+    /// JSON object
+    Json,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct StatsArgs {
+    /// Input file (XES, gz-compressed XES or CSV)
+    input: PathBuf,
+    #[clap(long, value_enum, default_value_t = ReportFormat::Table)]
+    format: ReportFormat,
+    /// Skip (and count) events and traces with values that can not be parsed instead of aborting
+    ///
+    /// Statistics are computed over the remaining events and traces
+    #[clap(long)]
+    skip_invalid: bool,
+    #[command(flatten)]
+    input_args: InputArgs,
+}
+
+/// Name of the XES type of an attribute value
+pub fn attribute_type_name(value: &AttributeValue) -> &'static str {
+    match value {
+        AttributeValue::String(_) => "string",
+        AttributeValue::Date(_) => "date",
+        AttributeValue::Int(_) => "int",
+        AttributeValue::Float(_) => "float",
+        AttributeValue::Boolean(_) => "boolean",
+        AttributeValue::ID(_) => "id",
+        AttributeValue::List(_) => "list",
+        AttributeValue::Container(_) => "container",
+        AttributeValue::None() => "none",
+    }
+}
+
+/// Number of occurrences of each attribute key, per value type
+type AttributeInventory = BTreeMap<String, BTreeMap<&'static str, u64>>;
+
+fn add_to_inventory(inventory: &mut AttributeInventory, attributes: &Attributes) {
+    for a in attributes {
+        *inventory
+            .entry(a.key.clone())
+            .or_default()
+            .entry(attribute_type_name(&a.value))
+            .or_default() += 1;
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TraceLengths {
+    pub min: usize,
+    pub max: usize,
+    pub mean: f64,
+    pub median: usize,
+    pub p90: usize,
+    /// Number of traces for each trace length
+    pub histogram: BTreeMap<usize, u64>,
+}
+
+impl TraceLengths {
+    fn from_histogram(histogram: BTreeMap<usize, u64>) -> Option<Self> {
+        let traces: u64 = histogram.values().sum();
+        let events: u64 = histogram.iter().map(|(len, n)| *len as u64 * n).sum();
+        let quantile = |q: f64| {
+            let rank = ((traces as f64 * q).ceil() as u64).max(1);
+            let mut seen = 0;
+            histogram
+                .iter()
+                .find(|(_, n)| {
+                    seen += *n;
+                    seen >= rank
+                })
+                .map(|(len, _)| *len)
+                .unwrap_or_default()
+        };
+        Some(Self {
+            min: *histogram.keys().next()?,
+            max: *histogram.keys().next_back()?,
+            mean: events as f64 / traces as f64,
+            median: quantile(0.5),
+            p90: quantile(0.9),
+            histogram,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActivityCount {
+    pub activity: String,
+    pub count: u64,
+}
+
+/// Summary statistics of an event log
+#[derive(Debug, Serialize)]
+pub struct LogStats {
+    pub traces: u64,
+    pub events: u64,
+    /// Number of distinct activity sequences
+    pub variants: usize,
+    /// Activities and their number of occurrences (most frequent first)
+    pub activities: Vec<ActivityCount>,
+    pub trace_lengths: Option<TraceLengths>,
+    /// Earliest event timestamp
+    pub first_timestamp: Option<DateTime<Utc>>,
+    /// Latest event timestamp
+    pub last_timestamp: Option<DateTime<Utc>>,
+    pub log_attributes: AttributeInventory,
+    pub trace_attributes: AttributeInventory,
+    pub event_attributes: AttributeInventory,
+}
+
+/// Collects statistics while streaming traces
+#[derive(Debug, Default)]
+pub struct StatsCollector {
+    traces: u64,
+    events: u64,
+    /// Distinct activity sequences
+    variants: HashSet<Variant>,
+    interner: VariantInterner,
+    activities: HashMap<String, u64>,
+    trace_lengths: BTreeMap<usize, u64>,
+    time_span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    log_attributes: AttributeInventory,
+    trace_attributes: AttributeInventory,
+    event_attributes: AttributeInventory,
+}
+
+impl StatsCollector {
+    pub fn add_log_data(&mut self, log_data: &XESOuterLogData) {
+        add_to_inventory(&mut self.log_attributes, &log_data.log_attributes);
+    }
+
+    pub fn add_trace(&mut self, trace: &Trace) {
+        self.traces += 1;
+        self.events += trace.events.len() as u64;
+        let variant = self.interner.intern(trace);
+        self.variants.insert(variant);
+        *self.trace_lengths.entry(trace.events.len()).or_default() += 1;
+        if let Some((first, last)) = time_span(trace) {
+            self.time_span = Some(match self.time_span {
+                Some((min, max)) => (min.min(first), max.max(last)),
+                None => (first, last),
+            });
+        }
+        add_to_inventory(&mut self.trace_attributes, &trace.attributes);
+        for e in &trace.events {
+            if let Some(a) = activity(e) {
+                match self.activities.get_mut(a) {
+                    Some(n) => *n += 1,
+                    None => {
+                        self.activities.insert(a.clone(), 1);
+                    }
+                }
+            }
+            add_to_inventory(&mut self.event_attributes, &e.attributes);
+        }
+    }
+
+    pub fn finish(self) -> LogStats {
+        let mut activities: Vec<ActivityCount> = self
+            .activities
+            .into_iter()
+            .map(|(activity, count)| ActivityCount { activity, count })
+            .collect();
+        activities.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.activity.cmp(&b.activity))
+        });
+        LogStats {
+            traces: self.traces,
+            events: self.events,
+            variants: self.variants.len(),
+            activities,
+            trace_lengths: TraceLengths::from_histogram(self.trace_lengths),
+            first_timestamp: self.time_span.map(|(first, _)| first),
+            last_timestamp: self.time_span.map(|(_, last)| last),
+            log_attributes: self.log_attributes,
+            trace_attributes: self.trace_attributes,
+            event_attributes: self.event_attributes,
+        }
+    }
+}
+
+impl LogStats {
+    pub fn print_table(&self) {
+        println!("Traces:     {}", self.traces);
+        println!("Events:     {}", self.events);
+        println!("Variants:   {}", self.variants);
+        println!("Activities: {}", self.activities.len());
+        if let (Some(first), Some(last)) = (self.first_timestamp, self.last_timestamp) {
+            println!(
+                "Time span:  {} - {} ({} days)",
+                first.to_rfc3339(),
+                last.to_rfc3339(),
+                (last - first).num_days()
+            );
+        }
+        if let Some(l) = &self.trace_lengths {
+            println!(
+                "Trace length: min {}, mean {:.2}, median {}, p90 {}, max {}",
+                l.min, l.mean, l.median, l.p90, l.max
+            );
+        }
+
+        println!("\nMost frequent activities:");
+        let width = self
+            .activities
+            .iter()
+            .take(TABLE_ACTIVITY_LIMIT)
+            .map(|a| a.activity.len())
+            .max()
+            .unwrap_or_default();
+        for a in self.activities.iter().take(TABLE_ACTIVITY_LIMIT) {
+            println!(
+                "  {:width$}  {:>10}  {:>6.2}%",
+                a.activity,
+                a.count,
+                100.0 * a.count as f64 / self.events as f64
+            );
+        }
+        if self.activities.len() > TABLE_ACTIVITY_LIMIT {
+            println!(
+                "  ... and {} more",
+                self.activities.len() - TABLE_ACTIVITY_LIMIT
+            );
+        }
+
+        for (level, inventory) in [
+            ("Log", &self.log_attributes),
+            ("Trace", &self.trace_attributes),
+            ("Event", &self.event_attributes),
+        ] {
+            println!("\n{level} attributes:");
+            let width = inventory.keys().map(|k| k.len()).max().unwrap_or_default();
+            for (key, types) in inventory {
+                let types: Vec<String> = types.iter().map(|(t, n)| format!("{t} ({n})")).collect();
+                println!("  {key:width$}  {}", types.join(", "));
+            }
+        }
+    }
+}
+
+/// Run the `stats` subcommand
+pub fn run(args: StatsArgs) -> Result<(), StripError> {
+    let mut collector = StatsCollector::default();
+    let (log_data, skipped) =
+        input::read_single_input(&args.input, &args.input_args, args.skip_invalid, |trace| {
+            collector.add_trace(&trace)
+        })?;
+    collector.add_log_data(&log_data);
+    if !skipped.is_empty() {
+        eprintln!(
+            "Warning: {} (not included in the statistics)",
+            skipped.summary()
+        );
+    }
+
+    let stats = collector.finish();
+    match args.format {
+        ReportFormat::Table => stats.print_table(),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&stats).unwrap()),
+    }
+    Ok(())
+}
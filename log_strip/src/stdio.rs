@@ -0,0 +1,46 @@
+use std::{
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Path reading from stdin (as input) or writing to stdout (as output)
+pub const STDIO_PATH: &str = "-";
+
+/// Options for reading from stdin and writing to stdout (where the format can not be determined from the file extension)
+#[derive(clap::Args, Debug, Clone, Copy)]
+#[command(next_help_heading = "Standard Input/Output")]
+pub struct StdioArgs {
+    /// Decompress the XES read from stdin (input `-`) using gzip
+    #[clap(long)]
+    pub gzip_in: bool,
+    /// Compress the XES written to stdout (output `-`) using gzip
+    #[clap(long)]
+    pub gzip_out: bool,
+}
+
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == STDIO_PATH
+}
+
+/// Whether status messages are printed to stderr (as stdout is used for the output)
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn print_status_to_stderr() {
+    STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+pub fn status_to_stderr() -> bool {
+    STATUS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// Print a status message to stdout, or to stderr if stdout is used for the output
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::stdio::status_to_stderr() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+pub(crate) use status;
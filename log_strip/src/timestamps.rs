@@ -0,0 +1,379 @@
+use chrono::{prelude::*, Days, LocalResult, Months};
+use chrono_tz::Tz;
+use clap::ValueEnum;
+use process_mining::event_log::{AttributeValue, Attributes, Trace, XESEditableAttribute};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Granularity to which timestamps are coarsened
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeGranularity {
+    /// Keep timestamps as they are
+    None,
+    Second,
+    Minute,
+    Hour,
+    Day,
+    /// Weeks start on Monday
+    Week,
+    Month,
+}
+
+impl TimeGranularity {
+    /// Truncate the timestamp to the start of its second/minute/... (in UTC)
+    pub fn truncate(&self, d: DateTime<Utc>) -> DateTime<Utc> {
+        let day_start = || Utc.from_utc_datetime(&d.date_naive().and_time(NaiveTime::MIN));
+        match self {
+            TimeGranularity::None => d,
+            TimeGranularity::Second => d.with_nanosecond(0).unwrap(),
+            TimeGranularity::Minute => d.with_second(0).unwrap().with_nanosecond(0).unwrap(),
+            TimeGranularity::Hour => d
+                .with_minute(0)
+                .unwrap()
+                .with_second(0)
+                .unwrap()
+                .with_nanosecond(0)
+                .unwrap(),
+            TimeGranularity::Day => day_start(),
+            TimeGranularity::Week => {
+                day_start() - Days::new(d.weekday().num_days_from_monday() as u64)
+            }
+            TimeGranularity::Month => day_start().with_day(1).unwrap(),
+        }
+    }
+
+    /// Round the timestamp to the closest start of a second/minute/... (in UTC)
+    ///
+    /// Timestamps exactly in the middle are rounded up
+    pub fn round(&self, d: DateTime<Utc>) -> DateTime<Utc> {
+        let floor = self.truncate(d);
+        let ceil = match self {
+            TimeGranularity::None => return d,
+            TimeGranularity::Second => floor + chrono::Duration::seconds(1),
+            TimeGranularity::Minute => floor + chrono::Duration::minutes(1),
+            TimeGranularity::Hour => floor + chrono::Duration::hours(1),
+            TimeGranularity::Day => floor + Days::new(1),
+            TimeGranularity::Week => floor + Days::new(7),
+            TimeGranularity::Month => floor + Months::new(1),
+        };
+        if d - floor < ceil - d {
+            floor
+        } else {
+            ceil
+        }
+    }
+
+    /// Truncate (or round) the timestamp in the given timezone, e.g., to the local midnight for days
+    pub fn coarsen(&self, d: DateTime<Utc>, round: bool, tz: Tz) -> DateTime<Utc> {
+        // Coarsen the local date and time (represented as UTC) and convert the result back
+        let local = Utc.from_utc_datetime(&d.with_timezone(&tz).naive_local());
+        let coarsened = if round {
+            self.round(local)
+        } else {
+            self.truncate(local)
+        };
+        match tz.from_local_datetime(&coarsened.naive_utc()) {
+            LocalResult::Single(c) | LocalResult::Ambiguous(c, _) => c.with_timezone(&Utc),
+            // Local time skipped by a DST transition: Keep the offset of the original timestamp
+            LocalResult::None => coarsened - (local - d),
+        }
+    }
+}
+
+/// Parse a timezone as IANA name (e.g., `Europe/Berlin`) or `UTC`
+pub fn parse_timezone(s: &str) -> Result<Tz, String> {
+    s.parse().map_err(|_| {
+        format!("Unknown timezone {s:?} (expected an IANA name like Europe/Berlin, or UTC)")
+    })
+}
+
+/// RFC 3339 representation of the date in the given timezone (preserving the instant)
+pub fn format_date(d: &DateTime<Utc>, tz: Tz) -> String {
+    d.with_timezone(&tz).to_rfc3339()
+}
+
+/// Call `f` for every date value in the attributes (including nested attributes, lists and containers)
+pub fn for_each_date_mut<F: FnMut(&mut DateTime<Utc>)>(attributes: &mut Attributes, f: &mut F) {
+    for a in attributes.iter_mut() {
+        match &mut a.value {
+            AttributeValue::Date(d) => f(d),
+            AttributeValue::List(children) | AttributeValue::Container(children) => {
+                for_each_date_mut(children, f)
+            }
+            _ => {}
+        }
+        if let Some(own_attributes) = &mut a.own_attributes {
+            for_each_date_mut(own_attributes, f);
+        }
+    }
+}
+
+/// Parse a duration like `90s`, `30m`, `12h`, `7d` or `2w`
+pub fn parse_duration(s: &str) -> Result<chrono::Duration, String> {
+    let s = s.trim();
+    let unit_start = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("Missing unit in duration {s:?} (expected s, m, h, d or w)"))?;
+    let (amount, unit) = s.split_at(unit_start);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid amount in duration {s:?}"))?;
+    let duration = match unit {
+        "s" => chrono::Duration::try_seconds(amount),
+        "m" => chrono::Duration::try_minutes(amount),
+        "h" => chrono::Duration::try_hours(amount),
+        "d" => chrono::Duration::try_days(amount),
+        "w" => chrono::Duration::try_weeks(amount),
+        _ => {
+            return Err(format!(
+                "Invalid unit {unit:?} in duration {s:?} (expected s, m, h, d or w)"
+            ))
+        }
+    };
+    duration.ok_or_else(|| format!("Duration {s:?} is out of range"))
+}
+
+/// Format a duration in the largest unit of [`parse_duration`] which represents it exactly (e.g., `2d`)
+pub fn format_duration(d: chrono::Duration) -> String {
+    let secs = d.num_seconds();
+    [
+        ("w", 7 * 24 * 3600),
+        ("d", 24 * 3600),
+        ("h", 3600),
+        ("m", 60),
+    ]
+    .into_iter()
+    .find(|(_, unit_secs)| secs != 0 && secs % unit_secs == 0)
+    .map_or_else(
+        || format!("{secs}s"),
+        |(unit, unit_secs)| format!("{}{unit}", secs / unit_secs),
+    )
+}
+
+/// Parse a date given as RFC 3339 (e.g., `2023-01-01T12:00:00+01:00`), as date and time without timezone (interpreted as UTC) or as date only (midnight UTC)
+pub fn parse_date(s: &str) -> Result<DateTime<Utc>, String> {
+    parse_date_in(s, Tz::UTC)
+}
+
+/// Parse a date like [`parse_date`], but interpret dates without UTC offset in the given timezone
+pub fn parse_date_in(s: &str, tz: Tz) -> Result<DateTime<Utc>, String> {
+    if let Ok(d) = DateTime::parse_from_rfc3339(s) {
+        return Ok(d.with_timezone(&Utc));
+    }
+    if let Ok(d) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+        return from_local(d, tz);
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return from_local(d.and_time(NaiveTime::MIN), tz);
+    }
+    Err(format!(
+        "Invalid date {s:?} (expected e.g. 2023-01-01, 2023-01-01T12:00:00 or 2023-01-01T12:00:00+01:00)"
+    ))
+}
+
+/// Convert a local date and time in the given timezone to UTC
+///
+/// Ambiguous times (i.e., repeated by a DST transition) are interpreted as the earlier one
+pub fn from_local(d: NaiveDateTime, tz: Tz) -> Result<DateTime<Utc>, String> {
+    tz.from_local_datetime(&d)
+        .earliest()
+        .map(|d| d.with_timezone(&Utc))
+        .ok_or_else(|| {
+            format!("Date {d} does not exist in timezone {tz} (skipped by a DST transition)")
+        })
+}
+
+/// Deserialize an optional date string using [`parse_date`] (e.g., for dates in pipeline files)
+pub fn deserialize_optional_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| parse_date(&s))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parse a date using a chrono format string (e.g., `%d.%m.%Y %H:%M`)
+///
+/// Dates without a UTC offset (`%z`) are interpreted in the given timezone, and dates without a time as midnight
+pub fn parse_date_with_format(s: &str, format: &str, tz: Tz) -> Result<DateTime<Utc>, String> {
+    if let Ok(d) = DateTime::parse_from_str(s, format) {
+        return Ok(d.with_timezone(&Utc));
+    }
+    if let Ok(d) = NaiveDateTime::parse_from_str(s, format) {
+        return from_local(d, tz);
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(s, format) {
+        return from_local(d.and_time(NaiveTime::MIN), tz);
+    }
+    Err(format!("Invalid date {s:?} (expected format {format:?})"))
+}
+
+/// Shifts all dates of a trace by a random per-trace offset
+///
+/// As all dates of a trace are shifted by the same offset, their order and the durations between them are preserved
+/// (up to coarsening the dates afterwards).
+/// The offset of a trace is derived from the seed and its case ID (`concept:name`), so that it does not depend on
+/// the order of the traces or on other (e.g., filtered) traces. Offsets of traces without case ID are drawn one after another.
+pub struct TimeShifter {
+    seed: u64,
+    /// Offsets of traces without case ID
+    rng: ChaCha8Rng,
+    max_shift_secs: i64,
+    /// First trace whose dates could not be shifted (as they would be out of the supported range)
+    pub error: Option<String>,
+}
+
+impl TimeShifter {
+    /// Offsets are drawn uniformly from `[-max_shift, max_shift]` (in full seconds)
+    pub fn new(max_shift: chrono::Duration, seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            max_shift_secs: max_shift.num_seconds().abs(),
+            error: None,
+        }
+    }
+
+    /// Shift the dates of the trace
+    ///
+    /// Returns `false` if a date would be out of range (in which case the trace should be dropped and the error is recorded)
+    pub fn apply(&mut self, trace: &mut Trace) -> bool {
+        let case_id = trace
+            .attributes
+            .get_by_key("concept:name")
+            .and_then(|a| a.value.try_as_string())
+            .cloned();
+        let mut rng = match &case_id {
+            Some(case_id) => {
+                let mut hasher = Sha256::new();
+                hasher.update(self.seed.to_le_bytes());
+                hasher.update(case_id.as_bytes());
+                ChaCha8Rng::from_seed(hasher.finalize().into())
+            }
+            None => ChaCha8Rng::seed_from_u64(self.rng.gen()),
+        };
+        let offset =
+            chrono::Duration::seconds(rng.gen_range(-self.max_shift_secs..=self.max_shift_secs));
+        let mut out_of_range = None;
+        let mut shift = |d: &mut DateTime<Utc>| match d.checked_add_signed(offset) {
+            Some(shifted) => *d = shifted,
+            None => out_of_range = Some(*d),
+        };
+        for_each_date_mut(&mut trace.attributes, &mut shift);
+        for e in trace.events.iter_mut() {
+            for_each_date_mut(&mut e.attributes, &mut shift);
+        }
+        if let Some(d) = out_of_range {
+            if self.error.is_none() {
+                let trace = match &case_id {
+                    Some(case_id) => format!("trace {case_id:?}"),
+                    None => "a trace without case ID".to_string(),
+                };
+                self.error = Some(format!(
+                    "Failed to shift date {d} of {trace} by {} (out of the supported date range)",
+                    format_duration(offset)
+                ));
+            }
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().to_utc()
+    }
+
+    #[test]
+    fn coarsen_in_utc() {
+        let d = utc("2023-03-15T13:47:31.250Z");
+        let coarsen = |granularity: TimeGranularity, round| granularity.coarsen(d, round, Tz::UTC);
+        assert_eq!(coarsen(TimeGranularity::None, false), d);
+        assert_eq!(
+            coarsen(TimeGranularity::Second, false),
+            utc("2023-03-15T13:47:31Z")
+        );
+        assert_eq!(
+            coarsen(TimeGranularity::Minute, false),
+            utc("2023-03-15T13:47:00Z")
+        );
+        assert_eq!(
+            coarsen(TimeGranularity::Hour, false),
+            utc("2023-03-15T13:00:00Z")
+        );
+        assert_eq!(
+            coarsen(TimeGranularity::Hour, true),
+            utc("2023-03-15T14:00:00Z")
+        );
+        assert_eq!(
+            coarsen(TimeGranularity::Day, false),
+            utc("2023-03-15T00:00:00Z")
+        );
+        // 2023-03-15 is a Wednesday
+        assert_eq!(
+            coarsen(TimeGranularity::Week, false),
+            utc("2023-03-13T00:00:00Z")
+        );
+        assert_eq!(
+            coarsen(TimeGranularity::Month, false),
+            utc("2023-03-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90s"), Ok(chrono::Duration::seconds(90)));
+        assert_eq!(parse_duration("15m"), Ok(chrono::Duration::minutes(15)));
+        assert_eq!(parse_duration(" 12h "), Ok(chrono::Duration::hours(12)));
+        assert_eq!(parse_duration("30d"), Ok(chrono::Duration::days(30)));
+        assert_eq!(parse_duration("2w"), Ok(chrono::Duration::weeks(2)));
+    }
+
+    #[test]
+    fn parse_duration_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("-5d").is_err());
+        assert!(parse_duration("5y").is_err());
+        assert!(parse_duration("5 d").is_err());
+        assert!(parse_duration("9999999999999999w").is_err());
+    }
+
+    #[test]
+    fn coarsen_in_timezone() {
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        // 00:30 local time on the next day
+        let d = utc("2023-03-15T23:30:00Z");
+        assert_eq!(
+            TimeGranularity::Day.coarsen(d, false, berlin),
+            utc("2023-03-15T23:00:00Z")
+        );
+        // After the DST transition, the local midnight is still at UTC+1
+        let d = utc("2023-03-26T12:00:00Z");
+        assert_eq!(
+            TimeGranularity::Day.coarsen(d, false, berlin),
+            utc("2023-03-25T23:00:00Z")
+        );
+    }
+
+    #[test]
+    fn coarsen_to_skipped_local_time() {
+        // The local midnight of 2018-11-04 was skipped in São Paulo (DST started at 00:00),
+        // so the offset of the original timestamp is kept
+        let sao_paulo: Tz = "America/Sao_Paulo".parse().unwrap();
+        let d = utc("2018-11-04T14:00:00Z");
+        assert_eq!(
+            TimeGranularity::Day.coarsen(d, false, sao_paulo),
+            utc("2018-11-04T02:00:00Z")
+        );
+    }
+}
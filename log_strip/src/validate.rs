@@ -0,0 +1,293 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Utc};
+use process_mining::event_log::{AttributeValue, Attributes, Trace, XESEditableAttribute};
+use serde::Serialize;
+
+use crate::{
+    error::StripError,
+    filter::activity,
+    input::{self, InputArgs, SkippedRecords},
+    stats::{attribute_type_name, ReportFormat},
+    timestamps::parse_date,
+};
+
+/// Maximum number of example locations listed per issue kind
+const MAX_EXAMPLES: usize = 5;
+
+#[derive(clap::Args, Debug)]
+pub struct ValidateArgs {
+    /// Input file (XES, gz-compressed XES or CSV)
+    input: PathBuf,
+    #[clap(long, value_enum, default_value_t = ReportFormat::Table)]
+    format: ReportFormat,
+    /// Report timestamps before this date as implausible
+    #[clap(long, value_name = "DATE", value_parser = parse_date, default_value = "1990-01-01")]
+    min_date: DateTime<Utc>,
+    /// Report timestamps after this date as implausible (default: now, i.e., timestamps in the future)
+    #[clap(long, value_name = "DATE", value_parser = parse_date)]
+    max_date: Option<DateTime<Utc>>,
+    #[command(flatten)]
+    input_args: InputArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    MissingCaseId,
+    DuplicateCaseId,
+    MissingActivity,
+    MissingTimestamp,
+    InvalidTimestamp,
+    NonMonotonicTimestamp,
+    ImplausibleTimestamp,
+    InconsistentAttributeType,
+    UnparseableRecord,
+}
+
+impl IssueKind {
+    fn description(&self) -> &'static str {
+        match self {
+            IssueKind::MissingCaseId => "Traces without case ID (concept:name)",
+            IssueKind::DuplicateCaseId => "Duplicate case IDs",
+            IssueKind::MissingActivity => "Events without activity (concept:name)",
+            IssueKind::MissingTimestamp => "Events without timestamp (time:timestamp)",
+            IssueKind::InvalidTimestamp => "Events with a timestamp which is not a date",
+            IssueKind::NonMonotonicTimestamp => "Events with a timestamp before the previous event",
+            IssueKind::ImplausibleTimestamp => "Timestamps outside of the plausible range",
+            IssueKind::InconsistentAttributeType => "Attributes with inconsistent types",
+            IssueKind::UnparseableRecord => {
+                "Events, traces or rest of the input which could not be parsed (skipped)"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Issue {
+    pub count: u64,
+    /// Locations of the first occurrences
+    pub examples: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub traces: u64,
+    pub events: u64,
+    pub valid: bool,
+    pub issues: BTreeMap<IssueKind, Issue>,
+}
+
+/// Checks traces for data-quality issues while streaming
+struct Validator {
+    min_date: DateTime<Utc>,
+    max_date: DateTime<Utc>,
+    traces: u64,
+    events: u64,
+    case_ids: HashSet<String>,
+    /// Number of occurrences of each trace/event attribute key, per value type
+    trace_attribute_types: BTreeMap<String, BTreeMap<&'static str, u64>>,
+    event_attribute_types: BTreeMap<String, BTreeMap<&'static str, u64>>,
+    issues: BTreeMap<IssueKind, Issue>,
+}
+
+impl Validator {
+    fn new(min_date: DateTime<Utc>, max_date: DateTime<Utc>) -> Self {
+        Self {
+            min_date,
+            max_date,
+            traces: 0,
+            events: 0,
+            case_ids: HashSet::new(),
+            trace_attribute_types: BTreeMap::new(),
+            event_attribute_types: BTreeMap::new(),
+            issues: BTreeMap::new(),
+        }
+    }
+
+    fn report(&mut self, kind: IssueKind, location: impl FnOnce() -> String) {
+        let issue = self.issues.entry(kind).or_default();
+        issue.count += 1;
+        if issue.examples.len() < MAX_EXAMPLES {
+            issue.examples.push(location());
+        }
+    }
+
+    fn check_trace(&mut self, trace: &Trace) {
+        self.traces += 1;
+        let case_id = match trace
+            .attributes
+            .get_by_key("concept:name")
+            .and_then(|a| a.value.try_as_string())
+        {
+            Some(case_id) => {
+                if !self.case_ids.insert(case_id.clone()) {
+                    self.report(IssueKind::DuplicateCaseId, || format!("case {case_id:?}"));
+                }
+                format!("case {case_id:?}")
+            }
+            None => {
+                let trace_index = self.traces - 1;
+                self.report(IssueKind::MissingCaseId, || format!("trace {trace_index}"));
+                format!("trace {trace_index}")
+            }
+        };
+        count_types(&mut self.trace_attribute_types, &trace.attributes);
+
+        let mut previous: Option<DateTime<Utc>> = None;
+        for (i, e) in trace.events.iter().enumerate() {
+            self.events += 1;
+            let location = || format!("{case_id}, event {i}");
+            if activity(e).is_none() {
+                self.report(IssueKind::MissingActivity, location);
+            }
+            match e.attributes.get_by_key("time:timestamp").map(|a| &a.value) {
+                Some(AttributeValue::Date(timestamp)) => {
+                    if previous.is_some_and(|p| *timestamp < p) {
+                        self.report(IssueKind::NonMonotonicTimestamp, location);
+                    }
+                    if *timestamp < self.min_date || *timestamp > self.max_date {
+                        self.report(IssueKind::ImplausibleTimestamp, || {
+                            format!("{}: {}", location(), timestamp.to_rfc3339())
+                        });
+                    }
+                    previous = Some(*timestamp);
+                }
+                Some(value) => self.report(IssueKind::InvalidTimestamp, || match value {
+                    AttributeValue::String(s) => format!("{}: {s:?}", location()),
+                    value => format!("{}: {}", location(), attribute_type_name(value)),
+                }),
+                None => self.report(IssueKind::MissingTimestamp, location),
+            }
+            count_types(&mut self.event_attribute_types, &e.attributes);
+        }
+    }
+
+    /// Report the records skipped while reading (including the rest of a malformed or truncated XES input)
+    fn add_skipped(&mut self, skipped: &SkippedRecords) {
+        let count = skipped.events + skipped.traces + skipped.truncated_inputs;
+        if count == 0 {
+            return;
+        }
+        let issue = self.issues.entry(IssueKind::UnparseableRecord).or_default();
+        issue.count += count;
+        issue
+            .examples
+            .extend(skipped.reasons.iter().take(MAX_EXAMPLES).cloned());
+    }
+
+    fn finish(mut self) -> ValidationReport {
+        for (level, types) in [
+            ("trace", std::mem::take(&mut self.trace_attribute_types)),
+            ("event", std::mem::take(&mut self.event_attribute_types)),
+        ] {
+            for (key, types) in types.into_iter().filter(|(_, types)| types.len() > 1) {
+                let types: Vec<String> = types.iter().map(|(t, n)| format!("{t} ({n})")).collect();
+                self.report(IssueKind::InconsistentAttributeType, || {
+                    format!("{level} attribute {key:?}: {}", types.join(", "))
+                });
+            }
+        }
+        ValidationReport {
+            traces: self.traces,
+            events: self.events,
+            valid: self.issues.is_empty(),
+            issues: self.issues,
+        }
+    }
+}
+
+fn count_types(types: &mut BTreeMap<String, BTreeMap<&'static str, u64>>, attributes: &Attributes) {
+    for a in attributes {
+        // Unparseable XES values (read as `None`) are skipped as with `--skip-invalid`, so they never get here,
+        // but should not make a type inconsistent either
+        if matches!(a.value, AttributeValue::None()) {
+            continue;
+        }
+        *types
+            .entry(a.key.clone())
+            .or_default()
+            .entry(attribute_type_name(&a.value))
+            .or_default() += 1;
+    }
+}
+
+impl ValidationReport {
+    pub fn print_table(&self) {
+        println!("Traces: {}", self.traces);
+        println!("Events: {}", self.events);
+        if self.valid {
+            println!("\nNo issues found");
+            return;
+        }
+        for (kind, issue) in &self.issues {
+            println!("\n{}: {}", kind.description(), issue.count);
+            for example in &issue.examples {
+                println!("  {example}");
+            }
+            if issue.count > issue.examples.len() as u64 {
+                println!("  ...");
+            }
+        }
+    }
+}
+
+/// Run the `validate` subcommand
+///
+/// Returns `false` if any issues were found
+///
+/// Records which can not be parsed are skipped and reported (CSV rows with a missing or unparseable timestamp are kept)
+pub fn run(mut args: ValidateArgs) -> Result<bool, StripError> {
+    let mut validator = Validator::new(args.min_date, args.max_date.unwrap_or_else(Utc::now));
+    args.input_args.csv.lenient_timestamps = true;
+    let (_, skipped) = input::read_single_input(&args.input, &args.input_args, true, |trace| {
+        validator.check_trace(&trace)
+    })?;
+    validator.add_skipped(&skipped);
+
+    let report = validator.finish();
+    match args.format {
+        ReportFormat::Table => report.print_table(),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+    }
+    Ok(report.valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn validator() -> Validator {
+        Validator::new(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC)
+    }
+
+    #[test]
+    fn truncated_input_is_an_issue() {
+        let mut validator = validator();
+        let mut skipped = SkippedRecords::default();
+        skipped.skip_rest_of_input(
+            Path::new("log.xes"),
+            "Input ends before the closing </log> tag (truncated file?)",
+        );
+        validator.add_skipped(&skipped);
+        let report = validator.finish();
+        assert!(!report.valid);
+        let issue = &report.issues[&IssueKind::UnparseableRecord];
+        assert_eq!(issue.count, 1);
+        assert!(issue.examples[0].contains("</log>"));
+    }
+
+    #[test]
+    fn no_skipped_records_no_issue() {
+        let mut validator = validator();
+        validator.add_skipped(&SkippedRecords::default());
+        let report = validator.finish();
+        assert!(report.valid);
+        assert!(report.issues.is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use process_mining::event_log::Trace;
+
+use crate::filter::activity;
+
+/// Activity sequence of a trace, with activities identified by their index in a [`VariantInterner`]
+pub type Variant = Vec<u32>;
+
+/// Maps the activity sequences of traces to [`Variant`]s
+///
+/// Each distinct activity label is stored once, so holding many variants in memory is cheap.
+/// Events without activity are treated as having the empty activity.
+#[derive(Debug, Clone, Default)]
+pub struct VariantInterner {
+    activity_ids: HashMap<String, u32>,
+}
+
+impl VariantInterner {
+    /// Variant of the trace, assigning new IDs to activities not seen before
+    pub fn intern(&mut self, trace: &Trace) -> Variant {
+        trace
+            .events
+            .iter()
+            .map(|e| {
+                let activity = activity(e).map_or("", |a| a.as_str());
+                match self.activity_ids.get(activity) {
+                    Some(&id) => id,
+                    None => {
+                        let id = self.activity_ids.len() as u32;
+                        self.activity_ids.insert(activity.to_string(), id);
+                        id
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Variant of the trace, if all its activities were seen before
+    pub fn lookup(&self, trace: &Trace) -> Option<Variant> {
+        trace
+            .events
+            .iter()
+            .map(|e| {
+                let activity = activity(e).map_or("", |a| a.as_str());
+                self.activity_ids.get(activity).copied()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use process_mining::event_log::{Attributes, Event};
+
+    use super::*;
+
+    fn trace(activities: &str) -> Trace {
+        Trace {
+            attributes: Attributes::new(),
+            events: activities
+                .chars()
+                .map(|a| Event::new(a.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn intern_and_lookup() {
+        let mut interner = VariantInterner::default();
+        assert_eq!(interner.intern(&trace("ABA")), vec![0, 1, 0]);
+        assert_eq!(interner.intern(&trace("BC")), vec![1, 2]);
+        assert_eq!(interner.lookup(&trace("CAB")), Some(vec![2, 0, 1]));
+        assert_eq!(interner.lookup(&trace("AD")), None);
+        assert_eq!(interner.lookup(&trace("")), Some(vec![]));
+    }
+}
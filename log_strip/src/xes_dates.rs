@@ -0,0 +1,194 @@
+use std::io::{self, BufRead, Read};
+
+use chrono::{DateTime, NaiveDateTime};
+use chrono_tz::Tz;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader, Writer,
+};
+
+use crate::timestamps::{format_date, from_local};
+
+/// Copy of a `date` element with its value replaced by `f(value)` (if `f` returns a new value)
+fn map_date_value<F>(e: &BytesStart, f: &F) -> io::Result<Option<BytesStart<'static>>>
+where
+    F: Fn(&str) -> Result<Option<String>, String>,
+{
+    if e.name().as_ref() != b"date" {
+        return Ok(None);
+    }
+    let mut mapped = BytesStart::new(String::from_utf8_lossy(e.name().as_ref()).into_owned());
+    let mut changed = false;
+    for attr in e.attributes() {
+        let attr = attr.map_err(io::Error::other)?;
+        if attr.key.as_ref() == b"value" {
+            let value = attr.unescape_value().map_err(io::Error::other)?;
+            if let Some(new_value) = f(&value).map_err(io::Error::other)? {
+                mapped.push_attribute(("value", new_value.as_str()));
+                changed = true;
+                continue;
+            }
+        }
+        mapped.push_attribute(attr);
+    }
+    Ok(changed.then_some(mapped))
+}
+
+/// Copy the next XML event from `reader` to `writer`, mapping the values of `date` elements
+///
+/// Returns `false` at the end of the document
+fn copy_event<R, W, F>(
+    reader: &mut Reader<R>,
+    buf: &mut Vec<u8>,
+    writer: &mut Writer<W>,
+    f: &F,
+) -> io::Result<bool>
+where
+    R: BufRead,
+    W: io::Write,
+    F: Fn(&str) -> Result<Option<String>, String>,
+{
+    buf.clear();
+    let event = reader.read_event_into(buf).map_err(io::Error::other)?;
+    let event = match event {
+        Event::Eof => return Ok(false),
+        Event::Start(e) => match map_date_value(&e, f)? {
+            Some(mapped) => Event::Start(mapped),
+            None => Event::Start(e),
+        },
+        Event::Empty(e) => match map_date_value(&e, f)? {
+            Some(mapped) => Event::Empty(mapped),
+            None => Event::Empty(e),
+        },
+        event => event,
+    };
+    writer.write_event(event).map_err(io::Error::other)?;
+    Ok(true)
+}
+
+/// Rewrite all (RFC 3339) dates of the XES document in the given timezone, preserving the instant
+///
+/// The XES exporter of `process_mining` always writes dates in UTC
+pub fn to_timezone(xml: &[u8], tz: Tz) -> io::Result<Vec<u8>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let mut buf = Vec::new();
+    let convert = |value: &str| {
+        Ok(DateTime::parse_from_rfc3339(value)
+            .ok()
+            .map(|d| format_date(&d.to_utc(), tz)))
+    };
+    while copy_event(&mut reader, &mut buf, &mut writer, &convert)? {}
+    Ok(writer.into_inner())
+}
+
+/// Reader of a XES document which interprets dates without UTC offset in the given timezone
+///
+/// The XES parser of `process_mining` interprets such dates as UTC, so they are rewritten
+/// (as RFC 3339 with the offset of the timezone) before parsing.
+/// Dates which do not exist in the timezone (i.e., skipped by a DST transition) are read as IO errors.
+pub struct AssumeTimezoneReader<R: BufRead> {
+    reader: Reader<R>,
+    buf: Vec<u8>,
+    /// Rewritten XML which was not read yet (starting at `pos`)
+    out: Writer<Vec<u8>>,
+    pos: usize,
+    done: bool,
+    tz: Tz,
+}
+
+impl<R: BufRead> AssumeTimezoneReader<R> {
+    pub fn new(reader: R, tz: Tz) -> Self {
+        Self {
+            reader: Reader::from_reader(reader),
+            buf: Vec::new(),
+            out: Writer::new(Vec::new()),
+            pos: 0,
+            done: false,
+            tz,
+        }
+    }
+}
+
+impl<R: BufRead> Read for AssumeTimezoneReader<R> {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.out.get_ref().len() && !self.done {
+            self.out.get_mut().clear();
+            self.pos = 0;
+            let tz = self.tz;
+            let assume_timezone = |value: &str| {
+                if DateTime::parse_from_rfc3339(value).is_ok() {
+                    return Ok(None);
+                }
+                match NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f") {
+                    Ok(d) => from_local(d, tz).map(|d| Some(format_date(&d, tz))),
+                    Err(_) => Ok(None),
+                }
+            };
+            self.done = !copy_event(
+                &mut self.reader,
+                &mut self.buf,
+                &mut self.out,
+                &assume_timezone,
+            )?;
+        }
+        let pending = &self.out.get_ref()[self.pos..];
+        let n = pending.len().min(dst.len());
+        dst[..n].copy_from_slice(&pending[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn berlin() -> Tz {
+        "Europe/Berlin".parse().unwrap()
+    }
+
+    fn read_assuming(xml: &str, tz: Tz) -> io::Result<String> {
+        let mut out = String::new();
+        AssumeTimezoneReader::new(xml.as_bytes(), tz).read_to_string(&mut out)?;
+        Ok(out)
+    }
+
+    const UTC_LOG: &str = r#"<log><trace><date key="time:timestamp" value="2023-01-01T12:00:00+00:00"/><string key="a&amp;b" value="x &lt; &quot;y&quot;"/><date key="due &amp; paid" value="2023-07-01T12:30:00.500+00:00"><string key="note" value="&lt;date&gt;"/></date></trace></log>"#;
+
+    #[test]
+    fn to_timezone_round_trip() {
+        let berlin_log = to_timezone(UTC_LOG.as_bytes(), berlin()).unwrap();
+        let berlin_log = String::from_utf8(berlin_log).unwrap();
+        assert_eq!(
+            berlin_log,
+            r#"<log><trace><date key="time:timestamp" value="2023-01-01T13:00:00+01:00"/><string key="a&amp;b" value="x &lt; &quot;y&quot;"/><date key="due &amp; paid" value="2023-07-01T14:30:00.500+02:00"><string key="note" value="&lt;date&gt;"/></date></trace></log>"#
+        );
+        let utc_log = to_timezone(berlin_log.as_bytes(), Tz::UTC).unwrap();
+        assert_eq!(String::from_utf8(utc_log).unwrap(), UTC_LOG);
+    }
+
+    #[test]
+    fn to_timezone_keeps_unparseable_dates() {
+        let xml = r#"<log><date key="d" value="soon"/></log>"#;
+        assert_eq!(
+            to_timezone(xml.as_bytes(), berlin()).unwrap(),
+            xml.as_bytes()
+        );
+    }
+
+    #[test]
+    fn assume_timezone_for_naive_dates() {
+        let xml = r#"<log><trace><date key="a" value="2023-01-01T12:00:00"/><date key="b &amp; c" value="2023-07-01T12:00:00.250"/><date key="c" value="2023-07-01T12:00:00Z"/><string key="d" value="2023-07-01T12:00:00"/></trace></log>"#;
+        assert_eq!(
+            read_assuming(xml, berlin()).unwrap(),
+            r#"<log><trace><date key="a" value="2023-01-01T12:00:00+01:00"/><date key="b &amp; c" value="2023-07-01T12:00:00.250+02:00"/><date key="c" value="2023-07-01T12:00:00Z"/><string key="d" value="2023-07-01T12:00:00"/></trace></log>"#
+        );
+    }
+
+    #[test]
+    fn assume_timezone_rejects_skipped_local_times() {
+        let xml = r#"<log><date key="a" value="2023-03-26T02:30:00"/></log>"#;
+        assert!(read_assuming(xml, berlin()).is_err());
+    }
+}